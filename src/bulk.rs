@@ -0,0 +1,106 @@
+//! Constructing many sources at once, collecting per-file errors rather than
+//! stopping at the first one.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use import::PathPolicy;
+use {BranchData, CoverallsError, Source};
+
+
+/// Coverage for a single file to be turned into a `Source`
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct SourceInput {
+    /// Path to the file, either absolute or relative to the working directory
+    pub path: PathBuf,
+    /// Hits per line number
+    pub lines: HashMap<usize, u64>,
+    /// Branch data if available
+    pub branches: Option<Vec<BranchData>>,
+    /// Whether to include the file contents in the source
+    pub include_source: bool,
+}
+
+type BuildResult = (Vec<Source>, Vec<(PathBuf, CoverallsError)>);
+
+fn build(root: &Path, input: SourceInput) -> Result<Source, (PathBuf, CoverallsError)> {
+    Source::new_in_repo(root, &input.path, &input.lines, &input.branches,
+                        input.include_source, PathPolicy::default())
+        .map_err(|e| (input.path, e))
+}
+
+fn collect<I: Iterator<Item = Result<Source, (PathBuf, CoverallsError)>>>(results: I) -> BuildResult {
+    let mut sources = vec![];
+    let mut errors = vec![];
+    for result in results {
+        match result {
+            Ok(s) => sources.push(s),
+            Err(e) => errors.push(e),
+        }
+    }
+    sources.sort_by(|a, b| a.name().cmp(b.name()));
+    errors.sort_by(|a, b| a.0.cmp(&b.0));
+    (sources, errors)
+}
+
+/// Creates a source for each input relative to the repository `root`,
+/// returning the sources sorted by name and the paths which failed.
+pub fn build_sources<I: IntoIterator<Item = SourceInput>>(root: &Path, inputs: I) -> BuildResult {
+    collect(inputs.into_iter().map(|i| build(root, i)))
+}
+
+/// As `build_sources` but reads and hashes the files on the rayon thread
+/// pool. The output is identical to the serial version.
+#[cfg(feature = "rayon")]
+pub fn build_sources_parallel<I: IntoParallelIterator<Item = SourceInput>>(root: &Path, inputs: I) -> BuildResult {
+    let results: Vec<_> = inputs.into_par_iter().map(|i| build(root, i)).collect();
+    collect(results.into_iter())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::TempDir;
+    use bulk::*;
+
+    fn inputs(dir: &Path) -> Vec<SourceInput> {
+        let mut inputs = vec![];
+        for i in 0..300 {
+            let path = dir.join(format!("src/file_{}.rs", i));
+            // Every tenth file is missing to exercise error collection
+            if i % 10 != 0 {
+                fs::write(&path, format!("fn f{}() {{}}\n// {}\n", i, i)).unwrap();
+            }
+            let mut lines = HashMap::new();
+            lines.insert(1, i);
+            inputs.push(SourceInput { path, lines, branches: None, include_source: false });
+        }
+        inputs
+    }
+
+    #[test]
+    fn test_build_sources() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        let (sources, errors) = build_sources(dir.path(), inputs(dir.path()));
+        assert_eq!(sources.len(), 270);
+        assert_eq!(errors.len(), 30);
+        assert!(sources.windows(2).all(|w| w[0].name() < w[1].name()));
+        assert!(errors[0].0.ends_with("src/file_0.rs"));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_matches_serial() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        let inputs = inputs(dir.path());
+        let (serial, serial_errors) = build_sources(dir.path(), inputs.clone());
+        let (parallel, parallel_errors) = build_sources_parallel(dir.path(), inputs);
+        assert_eq!(serial, parallel);
+        let paths = |e: &[(PathBuf, CoverallsError)]| e.iter().map(|e| e.0.clone()).collect::<Vec<_>>();
+        assert_eq!(paths(&serial_errors), paths(&parallel_errors));
+    }
+}
@@ -0,0 +1,128 @@
+//! Reusing source digests between reports so files which haven't changed
+//! since the last build aren't read and hashed again.
+
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
+
+
+/// Identifies the contents of a file by where it is, when it was last
+/// modified and its size. A file rewritten within the filesystem's timestamp
+/// resolution without changing size will be missed.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct DigestKey {
+    pub path: PathBuf,
+    pub modified: SystemTime,
+    pub size: u64,
+}
+
+impl DigestKey {
+    /// The key for a file's current metadata
+    pub fn from_metadata<P: Into<PathBuf>>(path: P, metadata: &Metadata) -> io::Result<DigestKey> {
+        Ok(DigestKey {
+            path: path.into(),
+            modified: metadata.modified()?,
+            size: metadata.len(),
+        })
+    }
+
+    /// The key for the file at `path` as it is now
+    pub fn for_file(path: &Path) -> io::Result<DigestKey> {
+        DigestKey::from_metadata(path, &path.metadata()?)
+    }
+}
+
+/// What is remembered about a file, enough to build a `Source` without
+/// reading it
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct CachedDigest {
+    /// Hex MD5 of the file's bytes
+    pub digest: String,
+    /// Number of lines, not counting an empty line after a final newline as
+    /// with `TrailingLine::Ignore`
+    pub lines: usize,
+}
+
+/// Storage for digests used by `Source::new_with_cache`. Takes `&self` so a
+/// cache can be shared between threads building sources, implementations
+/// needing to mutate should use a lock. Backing it with a file lets digests
+/// survive between builds.
+pub trait DigestCache {
+    /// The digest stored for `key`, if any
+    fn get(&self, key: &DigestKey) -> Option<CachedDigest>;
+
+    /// Stores the digest computed for `key`
+    fn insert(&self, key: DigestKey, digest: CachedDigest);
+}
+
+/// A `DigestCache` held in memory for the life of the process
+#[derive(Debug, Default)]
+pub struct MemoryDigestCache {
+    digests: Mutex<HashMap<DigestKey, CachedDigest>>,
+}
+
+impl MemoryDigestCache {
+    pub fn new() -> MemoryDigestCache {
+        MemoryDigestCache::default()
+    }
+
+    /// Number of files with a stored digest
+    pub fn len(&self) -> usize {
+        self.digests.lock().unwrap_or_else(PoisonError::into_inner).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl DigestCache for MemoryDigestCache {
+    fn get(&self, key: &DigestKey) -> Option<CachedDigest> {
+        self.digests.lock().unwrap_or_else(PoisonError::into_inner).get(key).cloned()
+    }
+
+    fn insert(&self, key: DigestKey, digest: CachedDigest) {
+        self.digests.lock().unwrap_or_else(PoisonError::into_inner).insert(key, digest);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+    use tempfile::TempDir;
+    use cache::*;
+    use Source;
+
+    #[test]
+    fn test_cached_digest_used() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("lib.rs");
+        fs::write(&path, "fn a() {}\nfn b() {}\n").unwrap();
+        let mut lines = HashMap::new();
+        lines.insert(2, 1);
+        let cache = MemoryDigestCache::new();
+
+        let uncached = Source::new("src/lib.rs", &path, &lines, &None, false).unwrap();
+        let first = Source::new_with_cache("src/lib.rs", &path, &lines, &None, &cache).unwrap();
+        assert_eq!(first, uncached);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(Source::new_with_cache("src/lib.rs", &path, &lines, &None, &cache).unwrap(), first);
+        assert_eq!(cache.len(), 1);
+
+        // A matching entry is trusted without reading the file
+        cache.insert(DigestKey::for_file(&path).unwrap(), CachedDigest { digest: "cached".to_string(), lines: 2 });
+        let source = Source::new_with_cache("src/lib.rs", &path, &lines, &None, &cache).unwrap();
+        assert_eq!(source.source_digest, "cached");
+
+        // Changing the size changes the key
+        fs::write(&path, "fn a() {}\nfn b() {}\nfn c() {}\n").unwrap();
+        let source = Source::new_with_cache("src/lib.rs", &path, &lines, &None, &cache).unwrap();
+        assert_eq!(source, Source::new("src/lib.rs", &path, &lines, &None, false).unwrap());
+        assert_eq!(cache.len(), 2);
+    }
+}
@@ -0,0 +1,93 @@
+//! Assembling a report from several threads at once, such as one per test
+//! binary, without the caller having to share a `&mut CoverallsReport`.
+
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+use {CoverallsReport, Identity, Source};
+
+
+/// Sources gathered from any number of threads, turned into a report with
+/// `finish`. Can be shared by reference or in an `Arc`.
+#[derive(Debug)]
+pub struct ReportCollector {
+    id: Identity,
+    /// Sources received so far by name, merged in `finish` so the result
+    /// doesn't depend on the order threads ran in
+    sources: Mutex<BTreeMap<String, Vec<Source>>>,
+}
+
+impl ReportCollector {
+    /// Creates an empty collector for a report with the given identity
+    pub fn new(id: Identity) -> ReportCollector {
+        ReportCollector {
+            id,
+            sources: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Adds a source, merged with any others for the same file when the
+    /// report is built
+    pub fn add_source(&self, source: Source) {
+        // A panic elsewhere can't leave the map half updated, so keep going
+        let mut sources = self.sources.lock().unwrap_or_else(PoisonError::into_inner);
+        sources.entry(source.name().to_string()).or_default().push(source);
+    }
+
+    /// Builds the report with sources sorted by name. Sources for the same
+    /// file are merged in a fixed order so the result, including the order
+    /// of branches, is the same whichever thread added its source first.
+    pub fn finish(self) -> CoverallsReport {
+        let sources = self.sources.into_inner().unwrap_or_else(PoisonError::into_inner);
+        let mut report = CoverallsReport::new(self.id);
+        for (_, mut same_file) in sources {
+            same_file.sort();
+            for source in same_file {
+                report.add_or_merge_source(source);
+            }
+        }
+        report
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+    use collector::*;
+    use BranchData;
+
+    fn collect() -> CoverallsReport {
+        let collector = Arc::new(ReportCollector::new(Identity::RepoToken(String::new().into())));
+        let handles = (0..12).map(|t| {
+            let collector = Arc::clone(&collector);
+            thread::spawn(move || {
+                for f in 0..5 {
+                    let branches = Some(vec![BranchData::new(1, 0, t % 3, 1)]);
+                    let source = Source::from_coverage_vec(format!("src/f{}.rs", (f + t) % 5), "",
+                                                           vec![Some(t as u64), None, Some(1)], &branches).unwrap();
+                    collector.add_source(source);
+                }
+            })
+        }).collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        Arc::try_unwrap(collector).unwrap().finish()
+    }
+
+    #[test]
+    fn test_concurrent_collection() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ReportCollector>();
+
+        let report = collect();
+        let names = report.source_files.iter().map(|s| s.name()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["src/f0.rs", "src/f1.rs", "src/f2.rs", "src/f3.rs", "src/f4.rs"]);
+        for source in &report.source_files {
+            assert_eq!(source.coverage().collect::<Vec<_>>(), vec![Some(66), None, Some(12)]);
+            assert_eq!(source.branches(), Some(&[1, 0, 0, 4,  1, 0, 1, 4,  1, 0, 2, 4][..]));
+        }
+        assert_eq!(report, collect());
+    }
+}
@@ -0,0 +1,203 @@
+//! Reading the `.coveralls.yml` file used by the Ruby and Python clients.
+
+use std::env::var;
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+use std::path::Path;
+use serde_yaml;
+use {CiService, CoverallsError, Identity, Service};
+
+
+/// Settings from a `.coveralls.yml` file. Keys this crate doesn't use are
+/// ignored.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default, Deserialize)]
+pub struct CoverallsYml {
+    /// Token for the repository on coveralls
+    #[serde(default)]
+    pub repo_token: Option<String>,
+    /// Name of the CI service e.g. "travis-pro"
+    #[serde(default)]
+    pub service_name: Option<String>,
+    /// Base URL of a Coveralls Enterprise install
+    #[serde(default)]
+    pub endpoint: Option<String>,
+}
+
+impl CoverallsYml {
+    /// Parses the configuration from YAML text
+    pub fn from_reader<R: Read>(reader: R) -> Result<CoverallsYml, CoverallsError> {
+        // An empty file is valid and has no settings
+        let config: Option<CoverallsYml> = serde_yaml::from_reader(reader)?;
+        Ok(config.unwrap_or_default())
+    }
+
+    /// Reads the configuration from the file at `path`
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<CoverallsYml, CoverallsError> {
+        CoverallsYml::from_reader(File::open(path)?)
+    }
+
+    /// As `read` but a missing file gives the empty configuration, as most
+    /// repositories don't have one
+    pub fn from_yaml_file<P: AsRef<Path>>(path: P) -> Result<CoverallsYml, CoverallsError> {
+        match File::open(path) {
+            Ok(f) => CoverallsYml::from_reader(f),
+            Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(CoverallsYml::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// URL to send jobs to, on the configured endpoint or coveralls.io
+    pub fn jobs_url(&self) -> String {
+        let base = self.endpoint.as_deref().unwrap_or("https://coveralls.io");
+        format!("{}/api/v1/jobs", base.trim_end_matches('/'))
+    }
+
+    /// Identity described by the file. With a `service_name` the service has
+    /// no job details, these can be taken from `Service::from_env`. Returns
+    /// None if neither a token nor a service is given.
+    pub fn identity(&self) -> Option<Identity> {
+        match (&self.repo_token, &self.service_name) {
+            (token, Some(name)) => {
+                let service = Service {
+                    name: name.parse::<CiService>().unwrap(),
+                    job_id: None,
+                    number: None,
+                    build_url: None,
+                    branch: None,
+                    pull_request: None,
+                    job_number: None,
+                };
+                Some(Identity::ServiceToken(token.clone().unwrap_or_default().into(), service))
+            },
+            (Some(token), None) => Some(Identity::RepoToken(token.clone().into())),
+            (None, None) => None,
+        }
+    }
+}
+
+impl Identity {
+    /// Works out the identity from, in order of preference, the `token`
+    /// argument, the environment and the `.coveralls.yml` in `repo_root`.
+    /// The token and service are resolved separately so a token from the
+    /// environment can be combined with a service from the file. Returns
+    /// None if none of them give a token or service.
+    pub fn from_config_or_env<P: AsRef<Path>>(token: Option<String>, repo_root: P) -> Result<Option<Identity>, CoverallsError> {
+        let token = token.or_else(|| var("COVERALLS_REPO_TOKEN").ok());
+        resolve_in(repo_root.as_ref(), token, Service::from_env())
+    }
+
+    /// Works out the identity the way the Ruby and Python clients do, with
+    /// the environment taking precedence over `.coveralls.yml` in
+    /// `repo_root`:
+    ///
+    /// 1. The token is `COVERALLS_REPO_TOKEN`, otherwise the file's
+    ///    `repo_token`.
+    /// 2. The service is the CI service detected from the environment,
+    ///    otherwise the file's `service_name`.
+    ///
+    /// With a service the result is a `ServiceToken`, with an empty token if
+    /// neither source gave one, and with just a token a `RepoToken`. If
+    /// neither is found `CoverallsError::NoIdentity` is returned. A missing
+    /// file isn't an error but one that can't be parsed is.
+    pub fn resolve<P: AsRef<Path>>(repo_root: P) -> Result<Identity, CoverallsError> {
+        Identity::from_config_or_env(None, repo_root)?.ok_or(CoverallsError::NoIdentity)
+    }
+}
+
+/// Resolves against the `.coveralls.yml` in `repo_root` with the token and
+/// service already read from the environment
+fn resolve_in(repo_root: &Path, token: Option<String>, env_service: Option<Service>) -> Result<Option<Identity>, CoverallsError> {
+    let yml = CoverallsYml::from_yaml_file(repo_root.join(".coveralls.yml"))?;
+    Ok(resolve(token, env_service, &yml))
+}
+
+fn resolve(token: Option<String>, env_service: Option<Service>, yml: &CoverallsYml) -> Option<Identity> {
+    let token = token.or_else(|| yml.repo_token.clone());
+    let service = env_service.or_else(|| match yml.identity() {
+        Some(Identity::ServiceToken(_, s)) => Some(s),
+        _ => None,
+    });
+    match (token, service) {
+        (token, Some(service)) => Some(Identity::ServiceToken(token.unwrap_or_default().into(), service)),
+        (Some(token), None) => Some(Identity::RepoToken(token.into())),
+        (None, None) => None,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::TempDir;
+    use config::*;
+
+    #[test]
+    fn test_identity() {
+        let yml = CoverallsYml::from_reader(&b"repo_token: abc123\n"[..]).unwrap();
+        assert_eq!(yml.identity(), Some(Identity::RepoToken("abc123".into())));
+
+        let yml = CoverallsYml::from_reader(&b"service_name: travis-pro\nrepo_token: abc123\nparallel: true\n"[..]).unwrap();
+        match yml.identity() {
+            Some(Identity::ServiceToken(ref token, ref service)) => {
+                assert_eq!(token.expose(), "abc123");
+                assert_eq!(service.name, CiService::TravisPro);
+                assert_eq!(service.job_id, None);
+            },
+            other => panic!("unexpected identity {:?}", other),
+        }
+
+        assert_eq!(CoverallsYml::from_reader(&b""[..]).unwrap().identity(), None);
+        assert!(CoverallsYml::from_reader(&b"repo_token: [unclosed"[..]).is_err());
+    }
+
+    #[test]
+    fn test_from_yaml_file() {
+        let full = CoverallsYml::from_yaml_file("tests/data/coveralls_yml/full.yml").unwrap();
+        assert_eq!(full.repo_token, Some("abc123".to_string()));
+        assert_eq!(full.service_name, Some("travis-pro".to_string()));
+        assert_eq!(full.jobs_url(), "https://coveralls.example.com/api/v1/jobs");
+
+        let endpoint = CoverallsYml::from_yaml_file("tests/data/coveralls_yml/endpoint_only.yml").unwrap();
+        assert_eq!(endpoint.endpoint, Some("https://coveralls.example.com".to_string()));
+        assert_eq!(endpoint.identity(), None);
+
+        let missing = CoverallsYml::from_yaml_file("tests/data/coveralls_yml/missing.yml").unwrap();
+        assert_eq!(missing, CoverallsYml::default());
+        assert_eq!(missing.jobs_url(), "https://coveralls.io/api/v1/jobs");
+    }
+
+    #[test]
+    fn test_resolution_order() {
+        let full = CoverallsYml::from_yaml_file("tests/data/coveralls_yml/full.yml").unwrap();
+        let endpoint = CoverallsYml::from_yaml_file("tests/data/coveralls_yml/endpoint_only.yml").unwrap();
+        let env_service = Service::travis_ci("42");
+
+        match resolve(Some("explicit".to_string()), None, &full) {
+            Some(Identity::ServiceToken(ref token, ref service)) => {
+                assert_eq!(token.expose(), "explicit");
+                assert_eq!(service.name, CiService::TravisPro);
+            },
+            other => panic!("unexpected identity {:?}", other),
+        }
+        assert_eq!(resolve(Some("env".to_string()), Some(env_service.clone()), &full),
+                   Some(Identity::ServiceToken("env".into(), env_service.clone())));
+        assert_eq!(resolve(None, None, &endpoint), None);
+        assert_eq!(resolve(Some("env".to_string()), None, &endpoint), Some(Identity::RepoToken("env".into())));
+        assert_eq!(resolve(None, Some(env_service.clone()), &endpoint),
+                   Some(Identity::ServiceToken(String::new().into(), env_service)));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(".coveralls.yml"), "repo_token: from_file\n").unwrap();
+        assert_eq!(resolve_in(dir.path(), None, None).unwrap(), Some(Identity::RepoToken("from_file".into())));
+        assert_eq!(resolve_in(dir.path(), Some("env".to_string()), Some(Service::travis_ci("42"))).unwrap(),
+                   Some(Identity::ServiceToken("env".into(), Service::travis_ci("42"))));
+        let empty = TempDir::new().unwrap();
+        assert_eq!(resolve_in(empty.path(), None, None).unwrap(), None);
+        fs::write(dir.path().join(".coveralls.yml"), "repo_token: [unclosed\n").unwrap();
+        assert!(resolve_in(dir.path(), None, None).is_err());
+        assert!(CoverallsError::NoIdentity.to_string().contains(".coveralls.yml"));
+    }
+}
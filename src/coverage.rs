@@ -0,0 +1,227 @@
+//! Sparse storage for line coverage. Only lines relevant to coverage are
+//! stored, the `null` entries for the other lines are produced while
+//! serializing so large files with few relevant lines stay small.
+
+use std::iter::FromIterator;
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use MergeStrategy;
+
+
+/// Hits per line of a file, equivalent to a `Vec<Option<u64>>` with an
+/// element for every line
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+pub(crate) struct Coverage {
+    /// Number of lines in the file
+    len: usize,
+    /// 0-based index and hits of the relevant lines, sorted by index
+    hits: Vec<(usize, u64)>,
+}
+
+impl Coverage {
+    /// Builds the coverage for `len` lines where the first line is numbered
+    /// `first` in `lines`. Lines past the end are dropped.
+    pub(crate) fn from_lines<'a, I>(lines: I, len: usize, first: usize) -> Coverage
+        where I: IntoIterator<Item = (&'a usize, &'a u64)>
+    {
+        let mut hits = lines.into_iter()
+            .filter(|&(&line, _)| line >= first && line - first < len)
+            .map(|(&line, &h)| (line - first, h))
+            .collect::<Vec<_>>();
+        hits.sort_unstable();
+        Coverage { len, hits }
+    }
+
+    /// Hits of the line at the 0-based `index`, None if it isn't relevant
+    pub(crate) fn get(&self, index: usize) -> Option<u64> {
+        self.hits.binary_search_by_key(&index, |&(i, _)| i)
+            .ok()
+            .map(|i| self.hits[i].1)
+    }
+
+    /// Every line in order, as they would appear in the expanded vector
+    pub(crate) fn iter(&self) -> Iter<'_> {
+        Iter { index: 0, len: self.len, hits: &self.hits }
+    }
+
+    /// 0-based index and hits of the relevant lines
+    pub(crate) fn relevant(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.hits.iter().cloned()
+    }
+
+    /// Adds `count` hits to the line at the 0-based `index`, making it
+    /// relevant if it wasn't. Lines past the end extend the coverage.
+    pub(crate) fn add(&mut self, index: usize, count: u64) {
+        match self.hits.binary_search_by_key(&index, |&(i, _)| i) {
+            Ok(i) => self.hits[i].1 = self.hits[i].1.saturating_add(count),
+            Err(i) => self.hits.insert(i, (index, count)),
+        }
+        self.len = self.len.max(index + 1);
+    }
+
+    /// Caps the hits of every line at `max`. Covered lines keep at least 1
+    /// hit so they remain covered even with a `max` of 0.
+    pub(crate) fn clamp_hits(&mut self, max: u64) {
+        for &mut (_, ref mut h) in &mut self.hits {
+            if *h > 0 {
+                *h = (*h).min(max).max(1);
+            }
+        }
+    }
+
+    /// Makes the lines whose 0-based index `keep` returns false for
+    /// irrelevant
+    pub(crate) fn retain<F: Fn(usize) -> bool>(&mut self, keep: F) {
+        self.hits.retain(|&(i, _)| keep(i));
+    }
+
+    /// Combines hits on lines relevant in both with `strategy`, and takes
+    /// lines only relevant in one as is
+    pub(crate) fn merge(&mut self, other: &Coverage, strategy: MergeStrategy) {
+        let mut merged = Vec::with_capacity(self.hits.len().max(other.hits.len()));
+        let (mut a, mut b) = (self.hits.iter().peekable(), other.hits.iter().peekable());
+        loop {
+            let next = match (a.peek(), b.peek()) {
+                (Some(&&(i, x)), Some(&&(j, y))) if i == j => {
+                    a.next();
+                    b.next();
+                    (i, strategy.combine(x, y))
+                },
+                (Some(&&x), Some(&&y)) if x.0 < y.0 => { a.next(); x },
+                (_, Some(&&y)) => { b.next(); y },
+                (Some(&&x), None) => { a.next(); x },
+                (None, None) => break,
+            };
+            merged.push(next);
+        }
+        self.hits = merged;
+        self.len = self.len.max(other.len);
+    }
+}
+
+impl From<Vec<Option<u64>>> for Coverage {
+    fn from(lines: Vec<Option<u64>>) -> Coverage {
+        lines.into_iter().collect()
+    }
+}
+
+impl FromIterator<Option<u64>> for Coverage {
+    fn from_iter<I: IntoIterator<Item = Option<u64>>>(iter: I) -> Coverage {
+        let mut coverage = Coverage::default();
+        for (i, hits) in iter.into_iter().enumerate() {
+            if let Some(h) = hits {
+                coverage.hits.push((i, h));
+            }
+            coverage.len = i + 1;
+        }
+        coverage
+    }
+}
+
+impl PartialEq<Vec<Option<u64>>> for Coverage {
+    fn eq(&self, other: &Vec<Option<u64>>) -> bool {
+        self.len == other.len() && self.iter().eq(other.iter().cloned())
+    }
+}
+
+impl Serialize for Coverage {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for Coverage {
+    fn deserialize<D>(deserializer: D) -> Result<Coverage, D::Error> where D: Deserializer<'de> {
+        Ok(Vec::<Option<u64>>::deserialize(deserializer)?.into())
+    }
+}
+
+/// Iterator over every line of a `Coverage`, yielding None for lines that
+/// aren't relevant
+pub(crate) struct Iter<'a> {
+    index: usize,
+    len: usize,
+    hits: &'a [(usize, u64)],
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Option<u64>;
+
+    fn next(&mut self) -> Option<Option<u64>> {
+        if self.index == self.len {
+            return None;
+        }
+        let item = match self.hits.first() {
+            Some(&(i, h)) if i == self.index => {
+                self.hits = &self.hits[1..];
+                Some(h)
+            },
+            _ => None,
+        };
+        self.index += 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.len - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> {}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::mem;
+    use serde_json;
+    use coverage::*;
+
+    #[test]
+    fn test_serialization_matches_vec() {
+        let fixtures = vec![
+            vec![],
+            vec![None],
+            vec![Some(0)],
+            vec![None, Some(2), Some(0), None, Some(1), None, None],
+            vec![Some(u64::MAX), None, Some(3)],
+        ];
+        for lines in fixtures {
+            let coverage = Coverage::from(lines.clone());
+            assert_eq!(serde_json::to_string(&coverage).unwrap(), serde_json::to_string(&lines).unwrap());
+            let parsed: Coverage = serde_json::from_str(&serde_json::to_string(&lines).unwrap()).unwrap();
+            assert_eq!(parsed, coverage);
+            assert_eq!(coverage, lines);
+            assert_eq!(coverage.iter().len(), lines.len());
+        }
+    }
+
+    #[test]
+    fn test_sparse_memory() {
+        let mut lines = HashMap::new();
+        lines.insert(10, 1);
+        lines.insert(500_000, 0);
+        lines.insert(2_000_000, 4);
+        let coverage = Coverage::from_lines(&lines, 1_000_000, 1);
+        assert_eq!(coverage.iter().len(), 1_000_000);
+        assert_eq!(coverage.hits.len(), 2);
+        assert_eq!(coverage.get(9), Some(1));
+        assert_eq!(coverage.get(499_999), Some(0));
+        assert_eq!(coverage.get(10), None);
+        // The expanded vector would need an Option<usize> for every line
+        let stored = mem::size_of::<Coverage>() + coverage.hits.len() * mem::size_of::<(usize, usize)>();
+        assert!(stored * 1000 < coverage.iter().len() * mem::size_of::<Option<usize>>());
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut a = Coverage::from(vec![None, Some(2), Some(2), Some(u64::MAX)]);
+        let b = Coverage::from(vec![Some(1), None, Some(3), Some(1), None, Some(5)]);
+        let mut max = a.clone();
+        a.merge(&b, MergeStrategy::Sum);
+        assert_eq!(a, vec![Some(1), Some(2), Some(5), Some(u64::MAX), None, Some(5)]);
+        max.merge(&b, MergeStrategy::Max);
+        assert_eq!(max, vec![Some(1), Some(2), Some(3), Some(u64::MAX), None, Some(5)]);
+    }
+}
@@ -0,0 +1,253 @@
+//! Comparison of a report against a baseline, for example the report of the
+//! previous build.
+
+use std::collections::HashMap;
+use std::fmt;
+use {CoverageStats, CoverallsReport, Source};
+
+
+/// How a file changed between the baseline and the current report
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    /// Present in both reports with the same source digest
+    Matched,
+    /// Present in both reports but the source digest differs, so line numbers
+    /// can't be compared
+    ContentChanged,
+    /// Only in the current report
+    Added,
+    /// Only in the baseline report
+    Removed,
+}
+
+/// Coverage change of a single file
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct FileDiff {
+    pub name: String,
+    pub status: FileStatus,
+    pub baseline: Option<CoverageStats>,
+    pub current: Option<CoverageStats>,
+    /// Lines covered in the baseline but not in the current report. Only
+    /// filled in for matched files
+    pub newly_uncovered: Vec<usize>,
+}
+
+impl FileDiff {
+    /// Change in line coverage percentage, if both sides have relevant lines
+    pub fn line_delta(&self) -> Option<f64> {
+        delta(self.baseline.as_ref(), self.current.as_ref())
+    }
+
+    fn is_unchanged(&self) -> bool {
+        self.status == FileStatus::Matched &&
+            self.newly_uncovered.is_empty() &&
+            self.baseline == self.current
+    }
+}
+
+/// Per-file and overall coverage changes between two reports
+#[derive(Clone, PartialEq, Debug, Serialize)]
+pub struct ReportDiff {
+    pub baseline: CoverageStats,
+    pub current: CoverageStats,
+    /// Files of both reports sorted by name
+    pub files: Vec<FileDiff>,
+}
+
+impl ReportDiff {
+    /// Change in overall line coverage percentage
+    pub fn line_delta(&self) -> Option<f64> {
+        delta(Some(&self.baseline), Some(&self.current))
+    }
+
+    /// Files whose line coverage dropped or which gained uncovered lines
+    pub fn regressions(&self) -> impl Iterator<Item = &FileDiff> {
+        self.files.iter().filter(|f| {
+            !f.newly_uncovered.is_empty() || f.line_delta().is_some_and(|d| d < 0.0)
+        })
+    }
+}
+
+fn delta(baseline: Option<&CoverageStats>, current: Option<&CoverageStats>) -> Option<f64> {
+    match (baseline.and_then(|s| s.line_percent()), current.and_then(|s| s.line_percent())) {
+        (Some(b), Some(c)) => Some(c - b),
+        _ => None,
+    }
+}
+
+fn newly_uncovered(baseline: &Source, current: &Source) -> Vec<usize> {
+    baseline.coverage.iter()
+        .zip(current.coverage.iter())
+        .enumerate()
+        .filter(|&(_, (b, c))| b.is_some_and(|h| h > 0) && c == Some(0))
+        .map(|(i, _)| i + 1)
+        .collect()
+}
+
+pub(crate) fn diff(current: &CoverallsReport, baseline: &CoverallsReport) -> ReportDiff {
+    let old = baseline.source_files.iter()
+        .map(|s| (s.name.as_str(), s))
+        .collect::<HashMap<_, _>>();
+    let mut files = Vec::new();
+    for source in &current.source_files {
+        let file = match old.get(source.name.as_str()) {
+            Some(base) if base.source_digest == source.source_digest => FileDiff {
+                name: source.name.clone(),
+                status: FileStatus::Matched,
+                baseline: Some(base.stats()),
+                current: Some(source.stats()),
+                newly_uncovered: newly_uncovered(base, source),
+            },
+            Some(base) => FileDiff {
+                name: source.name.clone(),
+                status: FileStatus::ContentChanged,
+                baseline: Some(base.stats()),
+                current: Some(source.stats()),
+                newly_uncovered: Vec::new(),
+            },
+            None => FileDiff {
+                name: source.name.clone(),
+                status: FileStatus::Added,
+                baseline: None,
+                current: Some(source.stats()),
+                newly_uncovered: Vec::new(),
+            },
+        };
+        files.push(file);
+    }
+    let names = current.source_files.iter()
+        .map(|s| s.name.as_str())
+        .collect::<Vec<_>>();
+    for source in baseline.source_files.iter().filter(|s| !names.contains(&s.name.as_str())) {
+        files.push(FileDiff {
+            name: source.name.clone(),
+            status: FileStatus::Removed,
+            baseline: Some(source.stats()),
+            current: None,
+            newly_uncovered: Vec::new(),
+        });
+    }
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    ReportDiff {
+        baseline: baseline.stats(),
+        current: current.stats(),
+        files,
+    }
+}
+
+fn write_percent(f: &mut fmt::Formatter, stats: Option<&CoverageStats>) -> fmt::Result {
+    match stats.and_then(|s| s.line_percent()) {
+        Some(p) => write!(f, "{:.1}%", p),
+        None => write!(f, "-"),
+    }
+}
+
+fn write_change(f: &mut fmt::Formatter,
+                baseline: Option<&CoverageStats>,
+                current: Option<&CoverageStats>) -> fmt::Result {
+    write_percent(f, baseline)?;
+    write!(f, " → ")?;
+    write_percent(f, current)?;
+    if let Some(d) = delta(baseline, current) {
+        write!(f, " ({:+.1})", d)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for ReportDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "total: ")?;
+        write_change(f, Some(&self.baseline), Some(&self.current))?;
+        writeln!(f)?;
+        for file in self.files.iter().filter(|f| !f.is_unchanged()) {
+            write!(f, "{}: ", file.name)?;
+            match file.status {
+                FileStatus::Added => {
+                    write!(f, "new file, ")?;
+                    write_percent(f, file.current.as_ref())?;
+                },
+                FileStatus::Removed => write!(f, "removed")?,
+                _ => write_change(f, file.baseline.as_ref(), file.current.as_ref())?,
+            }
+            match file.status {
+                FileStatus::ContentChanged => write!(f, ", changed content")?,
+                _ if !file.newly_uncovered.is_empty() => {
+                    write!(f, ", {} newly uncovered lines", file.newly_uncovered.len())?
+                },
+                _ => {},
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use diff::*;
+    use Identity;
+    use serde_json;
+
+    fn report(sources: Vec<(&str, &str, Vec<Option<u64>>)>) -> CoverallsReport {
+        let mut report = CoverallsReport::new(Identity::RepoToken("token".into()));
+        for (name, digest, coverage) in sources {
+            report.add_source(Source {
+                name: name.to_string(),
+                source_digest: digest.to_string(),
+                coverage: coverage.into(),
+                ..Default::default()
+            });
+        }
+        report
+    }
+
+    #[test]
+    fn test_file_statuses() {
+        let baseline = report(vec![
+            ("same.rs", "a", vec![Some(1), None]),
+            ("regressed.rs", "b", vec![Some(1), Some(2), Some(0)]),
+            ("edited.rs", "c", vec![Some(1)]),
+            ("gone.rs", "d", vec![Some(1)]),
+        ]);
+        let current = report(vec![
+            ("same.rs", "a", vec![Some(1), None]),
+            ("regressed.rs", "b", vec![Some(0), Some(2), Some(0)]),
+            ("edited.rs", "e", vec![Some(0), Some(0)]),
+            ("new.rs", "f", vec![Some(1), Some(0)]),
+        ]);
+        let diff = current.diff(&baseline);
+        let statuses = diff.files.iter()
+            .map(|f| (f.name.as_str(), f.status))
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, vec![
+            ("edited.rs", FileStatus::ContentChanged),
+            ("gone.rs", FileStatus::Removed),
+            ("new.rs", FileStatus::Added),
+            ("regressed.rs", FileStatus::Matched),
+            ("same.rs", FileStatus::Matched),
+        ]);
+        assert_eq!(diff.files[3].newly_uncovered, vec![1]);
+        assert!(diff.files[0].newly_uncovered.is_empty());
+        let regressed = diff.regressions().map(|f| f.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(regressed, vec!["edited.rs", "regressed.rs"]);
+        assert_eq!(diff.to_string(), "\
+total: 83.3% → 37.5% (-45.8)
+edited.rs: 100.0% → 0.0% (-100.0), changed content
+gone.rs: removed
+new.rs: new file, 50.0%
+regressed.rs: 66.7% → 33.3% (-33.3), 1 newly uncovered lines
+");
+    }
+
+    #[test]
+    fn test_serialize() {
+        let baseline = report(vec![("a.rs", "a", vec![Some(1)])]);
+        let current = report(vec![("a.rs", "a", vec![Some(0)])]);
+        let json = serde_json::to_value(current.diff(&baseline)).unwrap();
+        assert_eq!(json["files"][0]["status"], "matched");
+        assert_eq!(json["files"][0]["newly_uncovered"][0], 1);
+        assert_eq!(json["current"]["covered_lines"], 0);
+    }
+}
@@ -0,0 +1,144 @@
+//! Finding source files that never appear in the coverage output, such as
+//! modules no test loads, so they can be reported instead of silently
+//! raising the coverage percentage.
+
+use std::fs;
+use std::path::Path;
+use glob::Pattern;
+use ignore::{self, IgnoreOptions, IgnoreRules};
+use CoverallsError;
+
+
+/// Coverage given to files added because no coverage was recorded for them
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+pub enum UninstrumentedPolicy {
+    /// Every non-blank line is relevant with 0 hits
+    #[default]
+    Uncovered,
+    /// No line is relevant, so the file is listed without changing totals
+    Irrelevant,
+}
+
+/// Settings for walking the tree for uninstrumented files
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct DiscoverOptions {
+    /// Coverage given to the files found
+    pub policy: UninstrumentedPolicy,
+    /// Glob patterns for repo relative paths which shouldn't be added
+    pub exclude: Vec<String>,
+    /// Don't descend into directories named `target`
+    pub skip_target: bool,
+    /// Don't descend into directories whose names start with a `.`
+    pub skip_hidden: bool,
+    /// Ignore files and patterns applied while walking, by default the
+    /// repository's `.gitignore` and `.ignore` files
+    pub ignore: IgnoreOptions,
+}
+
+impl Default for DiscoverOptions {
+    fn default() -> Self {
+        DiscoverOptions {
+            policy: UninstrumentedPolicy::default(),
+            exclude: Vec::new(),
+            skip_target: true,
+            skip_hidden: true,
+            ignore: IgnoreOptions {
+                ignore_files: true,
+                extra_ignores: Vec::new(),
+            },
+        }
+    }
+}
+
+impl DiscoverOptions {
+    fn skips_dir(&self, name: &str) -> bool {
+        (self.skip_target && name == "target") || (self.skip_hidden && name.starts_with('.'))
+    }
+}
+
+/// Hits for a file with no recorded coverage under `policy`, as 1-based line
+/// numbers
+pub(crate) fn uninstrumented_lines(contents: &[u8], policy: UninstrumentedPolicy) -> Vec<(usize, u64)> {
+    match policy {
+        UninstrumentedPolicy::Irrelevant => Vec::new(),
+        UninstrumentedPolicy::Uncovered => {
+            contents.split(|&b| b == b'\n')
+                    .enumerate()
+                    .filter(|&(_, l)| l.iter().any(|b| !b.is_ascii_whitespace()))
+                    .map(|(i, _)| (i + 1, 0))
+                    .collect()
+        },
+    }
+}
+
+/// Repo relative names of the files under `root` matching any of `globs` and
+/// none of the exclusions or ignore rules, sorted by name
+pub fn find_files(root: &Path, globs: &[&str], options: &DiscoverOptions) -> Result<Vec<String>, CoverallsError> {
+    let globs = globs.iter()
+                     .map(|p| Pattern::new(p))
+                     .collect::<Result<Vec<Pattern>, _>>()?;
+    let exclude = options.exclude.iter()
+                                 .map(|p| Pattern::new(p))
+                                 .collect::<Result<Vec<Pattern>, _>>()?;
+    let mut rules = IgnoreRules::new(&options.ignore)?;
+    let mut found = Vec::new();
+    let mut dirs = vec![String::new()];
+    while let Some(dir) = dirs.pop() {
+        if options.ignore.ignore_files {
+            rules.read_dir(root, &dir)?;
+        }
+        for entry in fs::read_dir(root.join(&dir))? {
+            let entry = entry?;
+            let file_name = entry.file_name().to_string_lossy().into_owned();
+            let name = ignore::join(&dir, &file_name);
+            // Symlinked directories aren't followed so a cycle can't loop
+            if entry.file_type()?.is_dir() {
+                if !options.skips_dir(&file_name) && !rules.is_ignored(&name, true) {
+                    dirs.push(name);
+                }
+                continue;
+            }
+            if !root.join(&name).is_file() || rules.is_ignored(&name, false) {
+                continue;
+            }
+            if globs.iter().any(|p| p.matches(&name)) && !exclude.iter().any(|p| p.matches(&name)) {
+                found.push(name);
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::TempDir;
+    use discover::*;
+
+    #[test]
+    fn test_uninstrumented_lines() {
+        let contents = b"fn main() {\n\n    \t\n}\n";
+        assert_eq!(uninstrumented_lines(contents, UninstrumentedPolicy::Uncovered), vec![(1, 0), (4, 0)]);
+        assert!(uninstrumented_lines(contents, UninstrumentedPolicy::Irrelevant).is_empty());
+    }
+
+    #[test]
+    fn test_find_files_ignored() {
+        let dir = TempDir::new().unwrap();
+        for d in &["src/gen", "node_modules/pkg", "out"] {
+            fs::create_dir_all(dir.path().join(d)).unwrap();
+        }
+        fs::write(dir.path().join(".gitignore"), "/out/\n").unwrap();
+        fs::write(dir.path().join("src/.ignore"), "gen/*.rs\n!gen/mod.rs\n").unwrap();
+        for f in &["src/lib.rs", "src/gen/mod.rs", "src/gen/table.rs", "node_modules/pkg/x.rs", "out/build.rs"] {
+            fs::write(dir.path().join(f), "fn f() {}\n").unwrap();
+        }
+        let mut options = DiscoverOptions::default();
+        options.ignore.extra_ignores.push("node_modules/".to_string());
+        assert_eq!(find_files(dir.path(), &["**/*.rs"], &options).unwrap(), vec!["src/gen/mod.rs", "src/lib.rs"]);
+        options.ignore = IgnoreOptions::default();
+        assert_eq!(find_files(dir.path(), &["**/*.rs"], &options).unwrap().len(), 5);
+    }
+}
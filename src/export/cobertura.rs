@@ -0,0 +1,171 @@
+//! Writes sources as Cobertura XML.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use quick_xml::escape::escape;
+use {CoverageStats, CoverallsError, Source};
+
+
+/// Cobertura rates are fractions, with 1 when there's nothing to cover
+fn rate(percent: Option<f64>) -> f64 {
+    percent.map(|p| p / 100.0).unwrap_or(1.0)
+}
+
+/// Name of the package a source belongs to, its directory with `.`
+/// separators as in coverage.py
+fn package_name(name: &str) -> String {
+    match name.rfind('/') {
+        Some(i) => name[..i].replace('/', "."),
+        None => ".".to_string(),
+    }
+}
+
+/// Writes a Cobertura report with one package per directory, one class per
+/// source and its relevant lines. Lines with branch data are marked as
+/// branches with their condition coverage.
+pub fn write<W: Write>(sources: &[Source], w: &mut W, source_root: &Path) -> Result<(), CoverallsError> {
+    let mut packages: BTreeMap<String, Vec<&Source>> = BTreeMap::new();
+    let mut total = CoverageStats::default();
+    for source in sources {
+        packages.entry(package_name(&source.name)).or_default().push(source);
+        total.add(&source.stats());
+    }
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+
+    writeln!(w, r#"<?xml version="1.0" ?>"#)?;
+    writeln!(w, r#"<!DOCTYPE coverage SYSTEM "http://cobertura.sourceforge.net/xml/coverage-04.dtd">"#)?;
+    writeln!(w, r#"<coverage line-rate="{}" branch-rate="{}" lines-covered="{}" lines-valid="{}" branches-covered="{}" branches-valid="{}" complexity="0" version="0.1" timestamp="{}">"#,
+             rate(total.line_percent()), rate(total.branch_percent()),
+             total.covered_lines, total.relevant_lines,
+             total.covered_branches, total.branches, timestamp)?;
+    writeln!(w, "  <sources>")?;
+    writeln!(w, "    <source>{}</source>", escape(source_root.to_string_lossy()))?;
+    writeln!(w, "  </sources>")?;
+    writeln!(w, "  <packages>")?;
+    for (name, sources) in &packages {
+        let mut stats = CoverageStats::default();
+        for source in sources {
+            stats.add(&source.stats());
+        }
+        writeln!(w, r#"    <package name="{}" line-rate="{}" branch-rate="{}" complexity="0">"#,
+                 escape(name.as_str()), rate(stats.line_percent()), rate(stats.branch_percent()))?;
+        writeln!(w, "      <classes>")?;
+        for source in sources {
+            write_class(source, w)?;
+        }
+        writeln!(w, "      </classes>")?;
+        writeln!(w, "    </package>")?;
+    }
+    writeln!(w, "  </packages>")?;
+    writeln!(w, "</coverage>")?;
+    Ok(())
+}
+
+fn write_class<W: Write>(source: &Source, w: &mut W) -> Result<(), CoverallsError> {
+    let stats = source.stats();
+    let class = source.name.rsplit('/').next().unwrap_or("");
+    writeln!(w, r#"        <class name="{}" filename="{}" line-rate="{}" branch-rate="{}" complexity="0">"#,
+             escape(class), escape(source.name.as_str()),
+             rate(stats.line_percent()), rate(stats.branch_percent()))?;
+    writeln!(w, "          <methods/>")?;
+    writeln!(w, "          <lines>")?;
+    // (covered, total) branches for each line
+    let mut branches: HashMap<usize, (usize, usize)> = HashMap::new();
+    if let Some(ref b) = source.branches {
+        for chunk in b.chunks(4).filter(|c| c.len() == 4) {
+            let entry = branches.entry(chunk[0] as usize).or_insert((0, 0));
+            entry.0 += (chunk[3] > 0) as usize;
+            entry.1 += 1;
+        }
+    }
+    for (line, hits) in source.covered_lines() {
+        match branches.get(&line) {
+            Some(&(covered, total)) => {
+                writeln!(w, r#"            <line number="{}" hits="{}" branch="true" condition-coverage="{}% ({}/{})"/>"#,
+                         line, hits, covered * 100 / total, covered, total)?;
+            },
+            None => {
+                writeln!(w, r#"            <line number="{}" hits="{}" branch="false"/>"#, line, hits)?;
+            },
+        }
+    }
+    writeln!(w, "          </lines>")?;
+    writeln!(w, "        </class>")?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+    use quick_xml::Reader;
+    use quick_xml::events::Event;
+    use import::xml_attributes;
+    use export::cobertura::*;
+
+    fn source(name: &str, coverage: Vec<Option<u64>>, branches: Option<Vec<u64>>) -> Source {
+        Source {
+            name: name.to_string(),
+            coverage: coverage.into(),
+            branches,
+            ..Default::default()
+        }
+    }
+
+    /// Reads every element into (name, attributes), failing on malformed XML
+    fn elements(xml: &[u8]) -> Vec<(String, HashMap<String, String>)> {
+        let mut reader = Reader::from_reader(xml);
+        let mut buf = Vec::new();
+        let mut result = Vec::new();
+        loop {
+            match reader.read_event_into(&mut buf).unwrap() {
+                Event::Start(ref e) | Event::Empty(ref e) => {
+                    let name = String::from_utf8(e.name().as_ref().to_vec()).unwrap();
+                    result.push((name, xml_attributes(e).unwrap()));
+                },
+                Event::Eof => break,
+                _ => {},
+            }
+            buf.clear();
+        }
+        result
+    }
+
+    #[test]
+    fn test_structure() {
+        let sources = vec![
+            source("src/lib.rs", vec![None, Some(1), Some(0), Some(2)], Some(vec![4, 0, 0, 1,  4, 0, 1, 0])),
+            source("src/a & <b>/\"odd\".rs", vec![Some(1)], None),
+            source("build.rs", vec![Some(0), None], None),
+        ];
+        let mut out = Vec::new();
+        write(&sources, &mut out, Path::new("/repo")).unwrap();
+        let elements = elements(&out);
+        
+        let names = elements.iter().map(|e| e.0.as_str()).collect::<Vec<_>>();
+        assert_eq!(names.iter().filter(|n| **n == "package").count(), 3);
+        assert_eq!(names.iter().filter(|n| **n == "class").count(), 3);
+        assert_eq!(names.iter().filter(|n| **n == "line").count(), 5);
+
+        let coverage = &elements[0].1;
+        assert_eq!(coverage["lines-valid"], "5");
+        assert_eq!(coverage["lines-covered"], "3");
+        assert_eq!(coverage["line-rate"], "0.6");
+        assert_eq!(coverage["branch-rate"], "0.5");
+
+        let odd = elements.iter().find(|e| e.0 == "class" && e.1["name"] == "\"odd\".rs").unwrap();
+        assert_eq!(odd.1["filename"], "src/a & <b>/\"odd\".rs");
+        let odd_package = elements.iter().find(|e| e.0 == "package" && e.1["name"] == "src.a & <b>");
+        assert!(odd_package.is_some());
+
+        let branch_line = elements.iter().find(|e| e.0 == "line" && e.1["number"] == "4").unwrap();
+        assert_eq!(branch_line.1["branch"], "true");
+        assert_eq!(branch_line.1["condition-coverage"], "50% (1/2)");
+        
+        let root = elements.iter().find(|e| e.0 == "package" && e.1["name"] == ".").unwrap();
+        assert_eq!(root.1["line-rate"], "0");
+    }
+}
@@ -0,0 +1,170 @@
+//! Conversion to Codecov's JSON coverage format and upload via their v4 API.
+
+use std::collections::HashMap;
+use curl::easy::{Easy, List};
+use serde_json::{Map, Value};
+use {perform, CoverallsError, CoverallsReport, HttpResponse, Source};
+
+
+/// Builds `{"coverage": {"file": {"1": hits, "2": null, ...}}}` with string
+/// line number keys starting at 1. Lines where only some branches were taken
+/// are given as "covered/total" instead of a hit count.
+pub fn to_json(sources: &[Source]) -> Value {
+    let mut files = Map::new();
+    for source in sources {
+        // (covered, total) branches for each line
+        let mut branches: HashMap<usize, (usize, usize)> = HashMap::new();
+        if let Some(ref b) = source.branches {
+            for chunk in b.chunks(4).filter(|c| c.len() == 4) {
+                let entry = branches.entry(chunk[0] as usize).or_insert((0, 0));
+                entry.0 += (chunk[3] > 0) as usize;
+                entry.1 += 1;
+            }
+        }
+        let mut lines = Map::new();
+        for (i, hits) in source.coverage.iter().enumerate() {
+            let line = i + 1;
+            let value = match (hits, branches.get(&line)) {
+                (None, _) => Value::Null,
+                (Some(_), Some(&(covered, total))) if covered < total => {
+                    Value::String(format!("{}/{}", covered, total))
+                },
+                (Some(h), _) => Value::from(h),
+            };
+            lines.insert(line.to_string(), value);
+        }
+        files.insert(source.name.clone(), Value::Object(lines));
+    }
+    let mut result = Map::new();
+    result.insert("coverage".to_string(), Value::Object(files));
+    Value::Object(result)
+}
+
+/// Requests an upload location from Codecov then PUTs the report there.
+/// Returns the report's URL. Requests are made with the timeouts and retries
+/// set on `settings`.
+pub(crate) fn upload(settings: &CoverallsReport,
+                     report: &str,
+                     token: &str,
+                     commit: &str,
+                     endpoint: &str) -> Result<String, CoverallsError> {
+    let mut handle = Easy::new();
+    let url = format!("{}/upload/v4?package=coveralls-api&token={}&commit={}",
+                      endpoint.trim_end_matches('/'),
+                      handle.url_encode(token.as_bytes()),
+                      handle.url_encode(commit.as_bytes()));
+    let response = settings.with_retries(&mut handle, |h| {
+        h.url(&url)?;
+        h.post(true)?;
+        h.post_fields_copy(&[])?;
+        let mut headers = List::new();
+        headers.append("Accept: text/plain")?;
+        h.http_headers(headers)?;
+        perform(h, None)
+    })?;
+    let response = check_status(response)?;
+
+    // First line is the report URL and second the upload location
+    let mut lines = response.lines();
+    let report_url = lines.next().unwrap_or("").trim().to_string();
+    let upload_url = match lines.next() {
+        Some(u) => u.trim().to_string(),
+        None => return Err(CoverallsError::Parse(format!("Unexpected codecov response: {}", response))),
+    };
+
+    let response = settings.with_retries(&mut Easy::new(), |h| {
+        h.url(&upload_url)?;
+        let mut headers = List::new();
+        headers.append("Content-Type: application/json")?;
+        headers.append("x-amz-acl: public-read")?;
+        h.http_headers(headers)?;
+        perform(h, Some(report.as_bytes()))
+    })?;
+    check_status(response)?;
+    Ok(report_url)
+}
+
+/// The body of a successful response, otherwise the status as an error
+fn check_status(response: HttpResponse) -> Result<String, CoverallsError> {
+    match response.code {
+        200..=299 => Ok(String::from_utf8_lossy(&response.body).into_owned()),
+        code => Err(CoverallsError::Http(code)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde_json;
+    use export::codecov::*;
+    use mock::{self, MockServer};
+    use Identity;
+
+    fn report() -> CoverallsReport {
+        let mut report = CoverallsReport::new(Identity::RepoToken("token".into()));
+        report.add_source(Source { name: "src/lib.rs".to_string(), ..Default::default() });
+        report
+    }
+
+    #[test]
+    fn test_upload() {
+        let storage = MockServer::start(vec![
+            b"HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\nContent-Length: 0\r\n\r\n".to_vec(),
+            mock::response("200 OK", b""),
+        ]);
+        let location = format!("https://codecov.io/github/org/repo/commit/abc\n{}/bucket/report", storage.base_url());
+        let codecov = MockServer::start(vec![mock::response("200 OK", location.as_bytes())]);
+        let mut report = report();
+        report.set_retries(1);
+        let url = report.send_to_codecov("se cret", "abc", Some(&codecov.base_url())).unwrap();
+        assert_eq!(url, "https://codecov.io/github/org/repo/commit/abc");
+
+        let request = codecov.requests().remove(0);
+        assert!(request.headers.starts_with("POST /upload/v4?package=coveralls-api&token=se%20cret&commit=abc "),
+                "{}", request.headers);
+        let requests = storage.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].headers.starts_with("PUT /bucket/report "), "{}", requests[1].headers);
+        assert_eq!(requests[1].header("content-type"), Some("application/json"));
+        assert_eq!(requests[1].body, report.to_codecov_json().into_bytes());
+    }
+
+    #[test]
+    fn test_upload_without_location() {
+        let codecov = MockServer::start(vec![mock::response("200 OK", b"https://codecov.io/report")]);
+        match report().send_to_codecov("token", "abc", Some(&codecov.base_url())) {
+            Err(CoverallsError::Parse(ref e)) => assert!(e.contains("https://codecov.io/report"), "{}", e),
+            r => panic!("Expected a parse error got {:?}", r),
+        }
+        assert_eq!(codecov.requests().len(), 1);
+    }
+
+    #[test]
+    fn test_documented_example() {
+        // From Codecov's documentation of the JSON coverage format
+        let expected: Value = serde_json::from_str(r#"{
+            "coverage": {
+                "path/to/file.py": {"1": 0, "2": 1, "3": "1/2", "4": null}
+            }
+        }"#).unwrap();
+        let source = Source {
+            name: "path/to/file.py".to_string(),
+            coverage: vec![Some(0), Some(1), Some(3), None].into(),
+            branches: Some(vec![3, 0, 0, 3,  3, 0, 1, 0]),
+            ..Default::default()
+        };
+        assert_eq!(to_json(&[source]), expected);
+    }
+
+    #[test]
+    fn test_fully_covered_branches() {
+        let source = Source {
+            name: "src/lib.rs".to_string(),
+            coverage: vec![Some(5)].into(),
+            branches: Some(vec![1, 0, 0, 2,  1, 0, 1, 3]),
+            ..Default::default()
+        };
+        let json = to_json(&[source]);
+        assert_eq!(json["coverage"]["src/lib.rs"]["1"], 5);
+    }
+}
@@ -0,0 +1,159 @@
+//! Writes a static HTML coverage report for viewing offline.
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use {CoverageStats, CoverallsError, Source};
+
+
+const STYLE: &str = "body { font-family: sans-serif; }
+table { border-collapse: collapse; }
+td, th { padding: 2px 8px; text-align: left; }
+tr.total { font-weight: bold; border-top: 1px solid #333; }
+pre { margin: 0; }
+.gutter { text-align: right; color: #666; }
+.covered { background: #dfd; }
+.uncovered { background: #fdd; }
+";
+
+/// Escapes text for inclusion in HTML content or attribute values
+pub fn escape_html(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+fn format_percent(percent: Option<f64>) -> String {
+    match percent {
+        Some(p) => format!("{:.2}%", p),
+        None => "-".to_string(),
+    }
+}
+
+/// File name for a source's page, prefixed with its index so distinct names
+/// which sanitise to the same string don't collide
+fn page_name(index: usize, name: &str) -> String {
+    let sanitised = name.chars()
+                        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+                        .collect::<String>();
+    format!("{}_{}.html", index, sanitised)
+}
+
+/// Writes `index.html` and a page per source into `dir`, creating it if
+/// needed.
+pub fn write(sources: &[Source], dir: &Path, source_root: Option<&Path>) -> Result<(), CoverallsError> {
+    fs::create_dir_all(dir)?;
+    let mut index = BufWriter::new(File::create(dir.join("index.html"))?);
+    writeln!(index, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(index, "<title>Coverage report</title>\n<style>{}</style>\n</head>\n<body>", STYLE)?;
+    writeln!(index, "<h1>Coverage report</h1>\n<table>")?;
+    writeln!(index, "<tr><th>File</th><th>Relevant</th><th>Covered</th><th>Coverage</th></tr>")?;
+    let mut total = CoverageStats::default();
+    for (i, source) in sources.iter().enumerate() {
+        let stats = source.stats();
+        total.add(&stats);
+        let page = page_name(i, &source.name);
+        writeln!(index, "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                 escape_html(&page), escape_html(&source.name), stats.relevant_lines,
+                 stats.covered_lines, format_percent(stats.line_percent()))?;
+        write_source(source, &dir.join(page), source_root)?;
+    }
+    writeln!(index, "<tr class=\"total\"><td>Total</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+             total.relevant_lines, total.covered_lines, format_percent(total.line_percent()))?;
+    writeln!(index, "</table>\n</body>\n</html>")?;
+    index.flush()?;
+    Ok(())
+}
+
+fn write_source(source: &Source, path: &Path, source_root: Option<&Path>) -> Result<(), CoverallsError> {
+    let text = match (source.source.as_ref(), source_root) {
+        (Some(s), _) => Some(s.clone()),
+        (None, Some(root)) => fs::read_to_string(root.join(&source.name)).ok(),
+        (None, None) => None,
+    };
+    let code = text.as_ref().map(|t| t.lines().collect::<Vec<_>>()).unwrap_or_default();
+    let stats = source.stats();
+    let mut w = BufWriter::new(File::create(path)?);
+    writeln!(w, "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(w, "<title>{}</title>\n<style>{}</style>\n</head>\n<body>", escape_html(&source.name), STYLE)?;
+    writeln!(w, "<h1>{}</h1>", escape_html(&source.name))?;
+    writeln!(w, "<p>{} of {} relevant lines covered ({})</p>", stats.covered_lines, 
+             stats.relevant_lines, format_percent(stats.line_percent()))?;
+    writeln!(w, "<p><a href=\"index.html\">Back to index</a></p>\n<table>")?;
+    let line_count = code.len().max(source.coverage.iter().len());
+    for i in 0..line_count {
+        let hits = source.coverage.get(i);
+        let (class, gutter) = match hits {
+            Some(0) => ("uncovered", "0".to_string()),
+            Some(h) => ("covered", h.to_string()),
+            None => ("irrelevant", String::new()),
+        };
+        let line = code.get(i).map(|l| escape_html(l)).unwrap_or_default();
+        writeln!(w, "<tr class=\"{}\"><td class=\"gutter\">{}</td><td class=\"gutter\">{}</td><td><pre>{}</pre></td></tr>",
+                 class, i + 1, gutter, line)?;
+    }
+    writeln!(w, "</table>\n</body>\n</html>")?;
+    w.flush()?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+    use tempfile::TempDir;
+    use export::html::*;
+
+    #[test]
+    fn test_escaping_and_totals() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        let sources = vec![
+            Source {
+                name: "src/<b>&.rs".to_string(),
+                coverage: vec![Some(1), Some(0), None].into(),
+                source: Some("if a < b && c > d {\n    \"x\"\n}\n".to_string()),
+                ..Default::default()
+            },
+            Source {
+                name: "tests/example/mysource.rs".to_string(),
+                coverage: vec![None, None, None, None, Some(1), Some(1), None].into(),
+                ..Default::default()
+            },
+            Source {
+                name: "missing.rs".to_string(),
+                coverage: vec![Some(0)].into(),
+                ..Default::default()
+            },
+        ];
+        write(&sources, dir, Some(Path::new("."))).unwrap();
+
+        let index = fs::read_to_string(dir.join("index.html")).unwrap();
+        assert!(index.contains("src/&lt;b&gt;&amp;.rs"));
+        assert!(!index.contains("<b>&"));
+        assert!(index.contains("<tr class=\"total\"><td>Total</td><td>5</td><td>3</td><td>60.00%</td></tr>"));
+
+        let page = fs::read_to_string(dir.join("0_src__b__.rs.html")).unwrap();
+        assert!(page.contains("if a &lt; b &amp;&amp; c &gt; d {"));
+        assert!(page.contains("&quot;x&quot;"));
+        assert!(page.contains("<tr class=\"uncovered\"><td class=\"gutter\">2</td><td class=\"gutter\">0</td>"));
+
+        // Read from the source root
+        let page = fs::read_to_string(dir.join("1_tests_example_mysource.rs.html")).unwrap();
+        assert!(page.contains("println!(&quot;Hello world&quot;);"));
+
+        // Line number only view
+        let page = fs::read_to_string(dir.join("2_missing.rs.html")).unwrap();
+        assert!(page.contains("<tr class=\"uncovered\"><td class=\"gutter\">1</td><td class=\"gutter\">0</td><td><pre></pre></td></tr>"));
+    }
+}
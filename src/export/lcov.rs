@@ -0,0 +1,66 @@
+//! Writes sources as an LCOV tracefile.
+
+use std::io::Write;
+use {CoverallsError, Source};
+
+
+/// Writes an LCOV record for each source using the source name as the `SF`
+/// path. Irrelevant lines are omitted.
+pub fn write<W: Write>(sources: &[Source], w: &mut W) -> Result<(), CoverallsError> {
+    for source in sources {
+        writeln!(w, "SF:{}", source.name)?;
+        if let Some(ref branches) = source.branches {
+            for b in branches.chunks(4).filter(|c| c.len() == 4) {
+                writeln!(w, "BRDA:{},{},{},{}", b[0], b[1], b[2], b[3])?;
+            }
+        }
+        let mut found = 0;
+        let mut hit = 0;
+        for (line, hits) in source.covered_lines() {
+            writeln!(w, "DA:{},{}", line, hits)?;
+            found += 1;
+            if hits > 0 {
+                hit += 1;
+            }
+        }
+        writeln!(w, "LF:{}", found)?;
+        writeln!(w, "LH:{}", hit)?;
+        writeln!(w, "end_of_record")?;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use import::ImportContext;
+    use import::lcov::LcovParser;
+    use {CoverallsReport, Identity};
+
+    const TRACEFILE: &str = "SF:tests/example/mysource.rs
+BRDA:6,0,0,3
+BRDA:6,0,1,0
+DA:5,1
+DA:6,3
+DA:7,0
+LF:3
+LH:2
+end_of_record
+";
+
+    #[test]
+    fn test_round_trip() {
+        let ctx = ImportContext::new(env::current_dir().unwrap());
+        let mut report = CoverallsReport::new(Identity::RepoToken(String::new().into()));
+        report.import(&LcovParser, TRACEFILE.as_bytes(), &ctx).unwrap();
+
+        let mut exported = Vec::new();
+        report.write_lcov(&mut exported).unwrap();
+        assert_eq!(String::from_utf8(exported.clone()).unwrap(), TRACEFILE);
+
+        let mut reimported = CoverallsReport::new(Identity::RepoToken(String::new().into()));
+        reimported.import(&LcovParser, &exported[..], &ctx).unwrap();
+        assert_eq!(reimported.source_files, report.source_files);
+    }
+}
@@ -0,0 +1,8 @@
+//! Writers for other coverage formats, so the data gathered for coveralls can
+//! also feed other tools.
+
+pub mod cobertura;
+pub mod codecov;
+#[cfg(feature = "html")]
+pub mod html;
+pub mod lcov;
@@ -0,0 +1,197 @@
+//! Parsing the lines a change adds from `git diff -U0` output, for coverage
+//! of only the lines a pull request touches.
+
+use std::ops::Range;
+use std::str::FromStr;
+use CoverallsError;
+
+
+/// A file with content in the new side of a diff
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct ChangedFile {
+    /// Repo relative path after the change
+    pub name: String,
+    /// Path before the change if the file was renamed or copied
+    pub renamed_from: Option<String>,
+    /// Whether the file didn't exist before the change
+    pub new_file: bool,
+    /// Ranges of 1-based line numbers added or modified, in order
+    pub added: Vec<Range<usize>>,
+}
+
+impl ChangedFile {
+    /// Whether the 1-based `line` was added or modified
+    pub fn is_changed(&self, line: usize) -> bool {
+        self.added.iter().any(|r| r.contains(&line))
+    }
+
+    /// Number of lines added or modified
+    pub fn changed_lines(&self) -> usize {
+        self.added.iter().map(|r| r.len()).sum()
+    }
+}
+
+/// The files and lines changed by a diff, as printed by
+/// `git diff -U0 base...head`. Deleted files aren't included as they have no
+/// lines to cover.
+#[derive(Clone, Eq, PartialEq, Hash, Debug, Default)]
+pub struct GitDiff {
+    pub files: Vec<ChangedFile>,
+}
+
+impl GitDiff {
+    /// The changes to the file named `name` after the change
+    pub fn file(&self, name: &str) -> Option<&ChangedFile> {
+        self.files.iter().find(|f| f.name == name)
+    }
+}
+
+impl FromStr for GitDiff {
+    type Err = CoverallsError;
+
+    fn from_str(diff: &str) -> Result<GitDiff, CoverallsError> {
+        let mut files = Vec::new();
+        let mut current: Option<ChangedFile> = None;
+        let mut deleted = false;
+        // Position in the current hunk, tracked so content like `+++ x` isn't
+        // read as a header
+        let mut hunk = Hunk::default();
+        for line in diff.lines() {
+            if hunk.old_remaining > 0 || hunk.new_remaining > 0 {
+                if let Some(file) = current.as_mut() {
+                    hunk.read(line, file);
+                }
+                continue;
+            }
+            if let Some(header) = line.strip_prefix("diff --git ") {
+                files.extend(current.take().filter(|_| !deleted));
+                deleted = false;
+                current = Some(ChangedFile {
+                    name: header_new_name(header).unwrap_or_default(),
+                    ..ChangedFile::default()
+                });
+                continue;
+            }
+            let file = match current.as_mut() {
+                Some(f) => f,
+                None => continue,
+            };
+            if let Some(name) = line.strip_prefix("rename to ").or_else(|| line.strip_prefix("copy to ")) {
+                file.name = unquote(name);
+            } else if let Some(name) = line.strip_prefix("rename from ").or_else(|| line.strip_prefix("copy from ")) {
+                file.renamed_from = Some(unquote(name));
+            } else if line.starts_with("new file mode") {
+                file.new_file = true;
+            } else if line.starts_with("deleted file mode") || line == "+++ /dev/null" {
+                deleted = true;
+            } else if let Some(name) = line.strip_prefix("+++ ") {
+                let name = unquote(name);
+                file.name = name.strip_prefix("b/").map(str::to_string).unwrap_or(name);
+            } else if line.starts_with("@@ ") {
+                hunk = Hunk::parse(line)?;
+            }
+        }
+        files.extend(current.filter(|_| !deleted));
+        Ok(GitDiff { files })
+    }
+}
+
+/// Removes the quotes git puts around paths with unusual characters
+fn unquote(name: &str) -> String {
+    name.strip_prefix('"')
+        .and_then(|n| n.strip_suffix('"'))
+        .unwrap_or(name)
+        .to_string()
+}
+
+/// New path from `a/old b/new`, used when there's no `+++` line such as for
+/// an empty new file. Ambiguous if the paths contain " b/".
+fn header_new_name(header: &str) -> Option<String> {
+    header.rfind(" b/").map(|i| unquote(&header[i + 3..]))
+}
+
+/// Progress through the body of a hunk. Context lines are handled so diffs
+/// made without `-U0` work too.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+struct Hunk {
+    /// Line number in the new file of the next line
+    new_line: usize,
+    old_remaining: usize,
+    new_remaining: usize,
+}
+
+impl Hunk {
+    /// Reads the header `@@ -a,b +c,d @@`
+    fn parse(line: &str) -> Result<Hunk, CoverallsError> {
+        let invalid = || CoverallsError::Parse(format!("Invalid hunk header {:?}", line));
+        let mut ranges = line.split_whitespace().skip(1);
+        let mut side = |prefix: char| -> Result<(usize, usize), CoverallsError> {
+            let range = ranges.next().and_then(|r| r.strip_prefix(prefix)).ok_or_else(invalid)?;
+            let (start, count) = match range.split_once(',') {
+                Some((s, c)) => (s, c.parse().map_err(|_| invalid())?),
+                None => (range, 1),
+            };
+            Ok((start.parse().map_err(|_| invalid())?, count))
+        };
+        let (_, old_remaining) = side('-')?;
+        let (new_line, new_remaining) = side('+')?;
+        Ok(Hunk { new_line, old_remaining, new_remaining })
+    }
+
+    /// Reads a line of the body, recording it in `file` if it was added
+    fn read(&mut self, line: &str, file: &mut ChangedFile) {
+        match line.as_bytes().first() {
+            Some(b'+') => {
+                match file.added.last_mut() {
+                    Some(r) if r.end == self.new_line => r.end += 1,
+                    _ => file.added.push(self.new_line..self.new_line + 1),
+                }
+                self.new_line += 1;
+                self.new_remaining = self.new_remaining.saturating_sub(1);
+            },
+            Some(b'-') => self.old_remaining = self.old_remaining.saturating_sub(1),
+            Some(b'\\') => {},
+            // Context, which may have lost its leading space
+            _ => {
+                self.new_line += 1;
+                self.old_remaining = self.old_remaining.saturating_sub(1);
+                self.new_remaining = self.new_remaining.saturating_sub(1);
+            },
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use gitdiff::*;
+
+    #[test]
+    fn test_parse_diff() {
+        let diff = fs::read_to_string("tests/data/pr.diff").unwrap().parse::<GitDiff>().unwrap();
+        let names = diff.files.iter().map(|f| f.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["src/lib.rs", "src/parser.rs", "src/empty.rs", "src/new.rs"]);
+
+        let lib = diff.file("src/lib.rs").unwrap();
+        assert_eq!(lib.added, vec![3..5, 10..11]);
+        assert!(lib.is_changed(4) && !lib.is_changed(5) && !lib.is_changed(7));
+        assert_eq!(lib.changed_lines(), 3);
+
+        let renamed = diff.file("src/parser.rs").unwrap();
+        assert_eq!(renamed.renamed_from.as_deref(), Some("src/parse.rs"));
+        assert_eq!(renamed.added, vec![2..3]);
+
+        let empty = diff.file("src/empty.rs").unwrap();
+        assert!(empty.new_file && empty.added.is_empty());
+        let new = diff.file("src/new.rs").unwrap();
+        assert!(new.new_file);
+        assert_eq!(new.added, vec![1..3]);
+        assert!(diff.file("src/old.rs").is_none());
+
+        assert!("diff --git a/x b/x\n@@ -1 +q @@\n".parse::<GitDiff>().is_err());
+
+        let with_context = "diff --git a/x b/x\n--- a/x\n+++ b/x\n@@ -1,3 +1,4 @@\n a\n-b\n+c\n+d\n e\n";
+        assert_eq!(with_context.parse::<GitDiff>().unwrap().files[0].added, vec![2..4]);
+    }
+}
@@ -0,0 +1,236 @@
+//! A minimal reading of `.gitignore` and `.ignore` files so sources the
+//! repository ignores, like build output, are left out of reports. Supports
+//! the common subset of the gitignore syntax: comments, `!` negation, a
+//! trailing `/` for directories, anchoring with a `/` and `**`.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use glob::{MatchOptions, Pattern};
+use CoverallsError;
+
+
+/// Names of the files read in each directory, later files taking precedence
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// Which ignore rules apply when adding sources
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct IgnoreOptions {
+    /// Read `.gitignore` and `.ignore` files in the root and the directories
+    /// below it
+    pub ignore_files: bool,
+    /// Further patterns in the gitignore syntax relative to the root e.g.
+    /// `node_modules/`, taking precedence over the files
+    pub extra_ignores: Vec<String>,
+}
+
+impl IgnoreOptions {
+    /// Whether any rules could apply, so the tree needn't be read if not
+    pub fn is_active(&self) -> bool {
+        self.ignore_files || !self.extra_ignores.is_empty()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Rule {
+    /// Repo relative directory of the file the rule came from, empty for the
+    /// root
+    base: String,
+    pattern: Pattern,
+    negated: bool,
+    dir_only: bool,
+    /// Matched against the path from `base` rather than only the file name
+    anchored: bool,
+}
+
+impl Rule {
+    /// Parses a line of an ignore file, None for blank lines and comments
+    fn parse(base: &str, line: &str) -> Option<Result<Rule, CoverallsError>> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let pattern = match Pattern::new(line.trim_start_matches('/')) {
+            Ok(p) => p,
+            Err(e) => return Some(Err(e.into())),
+        };
+        Some(Ok(Rule { base: base.to_string(), pattern, negated, dir_only, anchored }))
+    }
+
+    fn matches(&self, name: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let relative = if self.base.is_empty() {
+            name
+        } else {
+            match name.strip_prefix(&self.base).and_then(|n| n.strip_prefix('/')) {
+                Some(n) => n,
+                None => return false,
+            }
+        };
+        let options = MatchOptions {
+            require_literal_separator: true,
+            ..MatchOptions::new()
+        };
+        if self.anchored {
+            self.pattern.matches_with(relative, options)
+        } else {
+            let file_name = relative.rsplit('/').next().unwrap_or(relative);
+            self.pattern.matches_with(file_name, options)
+        }
+    }
+}
+
+/// Ignore rules gathered from a repository
+#[derive(Clone, Debug, Default)]
+pub struct IgnoreRules {
+    /// Rules from ignore files, parents before the directories below them
+    rules: Vec<Rule>,
+    /// Rules from `extra_ignores`, checked after the files
+    extra: Vec<Rule>,
+}
+
+impl IgnoreRules {
+    /// Reads the rules for the repository at `root`. Ignore files in
+    /// directories which are themselves ignored aren't read, as with git.
+    pub fn load(root: &Path, options: &IgnoreOptions) -> Result<IgnoreRules, CoverallsError> {
+        let mut rules = IgnoreRules::new(options)?;
+        if options.ignore_files {
+            let mut dirs = vec![String::new()];
+            while let Some(dir) = dirs.pop() {
+                rules.read_dir(root, &dir)?;
+                for entry in fs::read_dir(root.join(&dir))? {
+                    let entry = entry?;
+                    if !entry.file_type()?.is_dir() || entry.file_name() == ".git" {
+                        continue;
+                    }
+                    let name = join(&dir, &entry.file_name().to_string_lossy());
+                    if !rules.is_ignored(&name, true) {
+                        dirs.push(name);
+                    }
+                }
+            }
+        }
+        Ok(rules)
+    }
+
+    /// Rules from `extra_ignores` only, for a caller reading the ignore files
+    /// of each directory as it walks the tree with `read_dir`
+    pub(crate) fn new(options: &IgnoreOptions) -> Result<IgnoreRules, CoverallsError> {
+        let extra = options.extra_ignores.iter()
+                                         .filter_map(|l| Rule::parse("", l))
+                                         .collect::<Result<Vec<_>, _>>()?;
+        Ok(IgnoreRules { rules: Vec::new(), extra })
+    }
+
+    /// Adds the rules in the ignore files of the repo relative `dir`. Lines
+    /// which aren't valid patterns are skipped, as git does.
+    pub(crate) fn read_dir(&mut self, root: &Path, dir: &str) -> Result<(), CoverallsError> {
+        for file in &IGNORE_FILES {
+            let contents = match fs::read_to_string(root.join(dir).join(file)) {
+                Ok(c) => c,
+                Err(ref e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            self.rules.extend(contents.lines().filter_map(|l| Rule::parse(dir, l)).filter_map(Result::ok));
+        }
+        Ok(())
+    }
+
+    /// Whether the repo relative `name` is ignored, either itself or because
+    /// a directory containing it is. The last matching rule decides, so a
+    /// negated rule can re-include a file but not one in an ignored directory.
+    pub fn is_ignored(&self, name: &str, is_dir: bool) -> bool {
+        let mut prefix = 0;
+        while let Some(i) = name[prefix..].find('/') {
+            prefix += i;
+            if self.decides(&name[..prefix], true) {
+                return true;
+            }
+            prefix += 1;
+        }
+        self.decides(name, is_dir)
+    }
+
+    /// Whether the last rule matching the path, ignoring its directories,
+    /// ignores it
+    fn decides(&self, name: &str, is_dir: bool) -> bool {
+        self.rules.iter()
+                  .chain(&self.extra)
+                  .rev()
+                  .find(|r| r.matches(name, is_dir))
+                  .is_some_and(|r| !r.negated)
+    }
+}
+
+/// Repo relative name of `name` in the directory `dir`
+pub(crate) fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::TempDir;
+    use ignore::*;
+
+    /// Repository with ignore files at the root and in `src`
+    fn ignored_tree() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        for d in &["src/gen", "target/debug", "node_modules/pkg", "build"] {
+            fs::create_dir_all(dir.path().join(d)).unwrap();
+        }
+        fs::write(dir.path().join(".gitignore"), "# build output\n/target/\n*.tmp.rs\nbuild/\n!keep.tmp.rs\n").unwrap();
+        fs::write(dir.path().join("src/.gitignore"), "gen/*\n!gen/api.rs\n").unwrap();
+        fs::write(dir.path().join("src/.ignore"), "local.rs\n").unwrap();
+        // Never read as `build` is ignored
+        fs::write(dir.path().join("build/.gitignore"), "!*\n").unwrap();
+        for f in &["src/lib.rs", "src/local.rs", "src/scratch.tmp.rs", "src/keep.tmp.rs", "src/gen/api.rs",
+                   "src/gen/other.rs", "target/debug/out.rs", "node_modules/pkg/index.rs", "build/out.rs"] {
+            fs::write(dir.path().join(f), "fn f() {}\n").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_nested_ignore_files() {
+        let dir = ignored_tree();
+        let options = IgnoreOptions {
+            ignore_files: true,
+            extra_ignores: vec!["node_modules/".to_string()],
+        };
+        let rules = IgnoreRules::load(dir.path(), &options).unwrap();
+        for name in &["src/lib.rs", "src/keep.tmp.rs", "src/gen/api.rs", "target.rs", "lib/target/x.rs"] {
+            assert!(!rules.is_ignored(name, false), "{}", name);
+        }
+        for name in &["src/local.rs", "src/scratch.tmp.rs", "src/gen/other.rs", "target/debug/out.rs",
+                      "node_modules/pkg/index.rs", "build/out.rs", "src/build/x.rs"] {
+            assert!(rules.is_ignored(name, false), "{}", name);
+        }
+        // Rules in `src` don't apply elsewhere
+        assert!(!rules.is_ignored("local.rs", false));
+
+        let rules = IgnoreRules::load(dir.path(), &IgnoreOptions::default()).unwrap();
+        assert!(!rules.is_ignored("target/debug/out.rs", false));
+        let bad = IgnoreOptions {
+            extra_ignores: vec!["[unclosed".to_string()],
+            ..Default::default()
+        };
+        assert!(IgnoreRules::load(dir.path(), &bad).is_err());
+    }
+}
@@ -0,0 +1,117 @@
+//! Parser for Clover XML reports as produced by PHPUnit and some JavaScript
+//! tooling.
+
+use std::io::{BufRead, BufReader, Read};
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use import::{numeric_attr, xml_attributes, CoverageParser, ImportContext, ParsedFile};
+use {BranchData, CoverallsError};
+
+
+/// Parses a Clover XML report. File paths are left as they appear in the
+/// report, typically absolute, `CoverallsReport::add_parsed_files` will
+/// resolve them against the repository root.
+///
+/// `stmt` lines give line hits. `cond` lines give both line hits and a pair
+/// of branches, the true branch first then the false branch.
+pub fn parse<R: BufRead>(reader: R) -> Result<Vec<ParsedFile>, CoverallsError> {
+    let mut reader = Reader::from_reader(reader);
+    let mut buf = Vec::new();
+    let mut result = Vec::new();
+    let mut current: Option<ParsedFile> = None;
+    let mut block = 0;
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                match e.local_name().as_ref() {
+                    b"file" => {
+                        let attrs = xml_attributes(e)?;
+                        // Some generators put the full path in `path` and just the
+                        // file name in `name`.
+                        let name = attrs.get("path").or_else(|| attrs.get("name")).ok_or_else(|| {
+                            CoverallsError::Parse("file without a name".to_string())
+                        })?;
+                        current = Some(ParsedFile::new(name.as_str()));
+                        block = 0;
+                    },
+                    b"line" => if let Some(ref mut file) = current {
+                        let attrs = xml_attributes(e)?;
+                        let line = numeric_attr(&attrs, "num")?;
+                        let count = numeric_attr(&attrs, "count")?;
+                        match attrs.get("type").map(|t| t.as_str()) {
+                            Some("stmt") => {
+                                file.lines.insert(line, count);
+                            },
+                            Some("cond") => {
+                                file.lines.insert(line, count);
+                                let truecount = numeric_attr(&attrs, "truecount")?;
+                                let falsecount = numeric_attr(&attrs, "falsecount")?;
+                                let branches = file.branches.get_or_insert_with(Vec::new);
+                                for (i, hits) in [truecount, falsecount].iter().enumerate() {
+                                    branches.push(BranchData {
+                                        line_number: line,
+                                        block_name: block,
+                                        branch_number: i,
+                                        hits: *hits,
+                                    });
+                                }
+                                block += 1;
+                            },
+                            _ => {},
+                        }
+                    },
+                    _ => {},
+                }
+            },
+            Event::End(ref e) if e.local_name().as_ref() == b"file" => {
+                if let Some(file) = current.take() {
+                    result.push(file);
+                }
+            },
+            Event::Eof => break,
+            _ => {},
+        }
+        buf.clear();
+    }
+    Ok(result)
+}
+
+
+/// `CoverageParser` for Clover XML reports
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct CloverParser;
+
+impl CoverageParser for CloverParser {
+    fn parse(&self, input: &mut dyn Read, _: &ImportContext) -> Result<Vec<ParsedFile>, CoverallsError> {
+        parse(BufReader::new(input))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use import::clover::*;
+
+    #[test]
+    fn test_parse_report() {
+        let data = include_bytes!("../../tests/data/clover.xml");
+        let files = parse(&data[..]).unwrap();
+        assert_eq!(files.len(), 2);
+
+        let user = &files[0];
+        assert_eq!(user.path, Path::new("/home/ci/project/src/User.php"));
+        assert_eq!(user.lines.get(&9), None);
+        assert_eq!(user.lines.get(&11), Some(&3));
+        assert_eq!(user.lines.get(&13), Some(&3));
+        assert_eq!(user.lines.get(&14), Some(&0));
+
+        let branches = user.branches.as_ref().unwrap();
+        assert_eq!(branches, &vec![
+            BranchData { line_number: 13, block_name: 0, branch_number: 0, hits: 3 },
+            BranchData { line_number: 13, block_name: 0, branch_number: 1, hits: 0 },
+        ]);
+
+        assert_eq!(files[1].path, Path::new("/tmp/vendor/lib/Helper.php"));
+    }
+}
@@ -0,0 +1,161 @@
+//! Parser for the `.gcov` text files produced by gcov for C and C++ code.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use import::{find_files, merge_duplicates, strip_prefix, CoverageParser, ImportContext, ParsedFile, PathPolicy};
+use {BranchData, CoverallsError};
+
+
+/// Parses a single `.gcov` file. The `Source:` header gives the file name,
+/// which has `prefix` removed to make it relative to the repository root.
+/// Returns None if the file has no `Source:` header.
+pub fn parse<R: BufRead>(reader: R, prefix: &Path) -> Result<Option<ParsedFile>, CoverallsError> {
+    let mut file: Option<ParsedFile> = None;
+    let mut branches = Vec::new();
+    let mut last_line = 0;
+    let mut block = 0;
+    let mut last_branch: Option<usize> = None;
+    for line in reader.lines() {
+        let line = line?;
+        if line.starts_with("branch") {
+            if let Some((number, hits)) = parse_branch(&line) {
+                // Branch numbers restart for each block on a line.
+                if last_branch.map(|b| number <= b).unwrap_or(false) {
+                    block += 1;
+                }
+                last_branch = Some(number);
+                branches.push(BranchData {
+                    line_number: last_line,
+                    block_name: block,
+                    branch_number: number,
+                    hits,
+                });
+            }
+            continue;
+        }
+        let mut parts = line.splitn(3, ':');
+        let (count, number, rest) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(c), Some(n), Some(r)) => (c.trim(), n.trim(), r),
+            _ => continue,
+        };
+        let number = match number.parse::<usize>() {
+            Ok(n) => n,
+            Err(_) => continue,
+        };
+        if number == 0 {
+            if rest.starts_with("Source:") && file.is_none() {
+                let path = Path::new(rest["Source:".len()..].trim());
+                file = Some(ParsedFile::new(strip_prefix(path, prefix)));
+            }
+            continue;
+        }
+        last_line = number;
+        block = 0;
+        last_branch = None;
+        if let Some(ref mut f) = file {
+            let hits = match count {
+                "-" => continue,
+                "#####" | "=====" => 0,
+                c => match c.trim_end_matches('*').parse::<u64>() {
+                    Ok(h) => h,
+                    Err(_) => continue,
+                },
+            };
+            let entry = f.lines.entry(number).or_insert(0);
+            *entry = entry.saturating_add(hits);
+        }
+    }
+    if let Some(ref mut f) = file {
+        if !branches.is_empty() {
+            f.branches = Some(branches);
+        }
+    }
+    Ok(file)
+}
+
+/// Parses a branch record such as `branch  0 taken 5` or
+/// `branch  1 never executed`. Percentages (gcov run without `-c`) are
+/// treated as a single hit when non-zero.
+fn parse_branch(line: &str) -> Option<(usize, u64)> {
+    let mut words = line.split_whitespace().skip(1);
+    let number = words.next()?.parse::<usize>().ok()?;
+    let hits = match words.next()? {
+        "taken" => {
+            let value = words.next()?;
+            if value.ends_with('%') {
+                let percent = value.trim_end_matches('%').parse::<f64>().ok()?;
+                (percent > 0.0) as u64
+            } else {
+                value.parse::<u64>().ok()?
+            }
+        },
+        "never" => 0,
+        _ => return None,
+    };
+    Some((number, hits))
+}
+
+/// Walks `dir` parsing every `.gcov` file found. Files for the same source
+/// (i.e. from multiple object files) are merged by summing the hits.
+pub fn parse_dir(dir: &Path, prefix: &Path) -> Result<Vec<ParsedFile>, CoverallsError> {
+    let mut files = Vec::new();
+    for path in find_files(dir, "gcov", &PathPolicy::default())? {
+        let reader = BufReader::new(File::open(&path)?);
+        if let Some(f) = parse(reader, prefix)? {
+            files.push(f);
+        }
+    }
+    Ok(merge_duplicates(files))
+}
+
+
+/// `CoverageParser` for a single `.gcov` file
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct GcovParser {
+    /// Prefix removed from the `Source:` header paths
+    pub prefix: PathBuf,
+}
+
+impl CoverageParser for GcovParser {
+    fn parse(&self, input: &mut dyn Read, _: &ImportContext) -> Result<Vec<ParsedFile>, CoverallsError> {
+        Ok(parse(BufReader::new(input), &self.prefix)?.into_iter().collect())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use import::gcov::*;
+
+    #[test]
+    fn test_parse_dir() {
+        let files = parse_dir(Path::new("tests/data/gcov"), Path::new("/home/ci/repo")).unwrap();
+        assert_eq!(files.len(), 2);
+
+        let foo = files.iter().find(|f| f.path == Path::new("src/foo.c")).unwrap();
+        assert_eq!(foo.lines.get(&1), None);
+        assert_eq!(foo.lines.get(&3), Some(&7));
+        assert_eq!(foo.lines.get(&4), Some(&0));
+        assert_eq!(foo.lines.get(&5), Some(&2));
+        assert_eq!(foo.lines.get(&6), Some(&7));
+
+        let branches = foo.branches.as_ref().unwrap();
+        assert_eq!(branches.len(), 2);
+        assert_eq!(branches[0], BranchData { line_number: 3, block_name: 0, branch_number: 0, hits: 7 });
+        assert_eq!(branches[1], BranchData { line_number: 3, block_name: 0, branch_number: 1, hits: 0 });
+
+        let bar = files.iter().find(|f| f.path == Path::new("/elsewhere/bar.c")).unwrap();
+        assert_eq!(bar.lines.get(&2), Some(&0));
+        assert!(bar.branches.is_none());
+    }
+
+    #[test]
+    fn test_branch_blocks() {
+        let data = "        -:    0:Source:a.c\n        4:    1:if (a && b)\nbranch  0 taken 3\nbranch  1 taken 1\nbranch  0 taken 50%\nbranch  1 never executed\n";
+        let file = parse(data.as_bytes(), Path::new("")).unwrap().unwrap();
+        let blocks = file.branches.unwrap().iter().map(|b| (b.block_name, b.branch_number, b.hits)).collect::<Vec<_>>();
+        assert_eq!(blocks, vec![(0, 0, 3), (0, 1, 1), (1, 0, 1), (1, 1, 0)]);
+    }
+}
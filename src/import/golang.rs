@@ -0,0 +1,183 @@
+//! Parser for the coverage profiles written by `go test -coverprofile`.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+use import::{CoverageParser, ImportContext, ParsedFile};
+use CoverallsError;
+
+
+/// Most lines a single block may span, anything longer is taken to be a
+/// corrupt profile rather than expanded
+const MAX_BLOCK_LINES: usize = 1_000_000;
+
+/// How hit counts in a profile should be interpreted
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub enum Mode {
+    /// Counts only record whether a block ran
+    Set,
+    /// Counts are the number of times a block ran (`count` and `atomic`)
+    Count,
+}
+
+/// Parses a go coverage profile. Each block expands to every line in its
+/// range, where blocks overlap the larger count is used. In `set` mode all
+/// hit lines get a count of 1. Blocks which end before they start or span
+/// more than `MAX_BLOCK_LINES` are rejected.
+///
+/// `mappings` are pairs of (module path prefix, repo relative directory)
+/// used to turn the package paths in the profile into repo relative ones,
+/// e.g. `("github.com/org/repo", "")`. The longest matching prefix wins and
+/// paths with no matching prefix are left as they are.
+pub fn parse<R: BufRead>(reader: R, mappings: &[(&str, &str)]) -> Result<Vec<ParsedFile>, CoverallsError> {
+    let mut mode = Mode::Set;
+    let mut files: Vec<ParsedFile> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(m) = line.strip_prefix("mode:") {
+            mode = match m.trim() {
+                "set" => Mode::Set,
+                "count" | "atomic" => Mode::Count,
+                m => return Err(CoverallsError::Parse(format!("Unknown coverage mode {}", m))),
+            };
+            continue;
+        }
+        let invalid = || CoverallsError::Parse(format!("Invalid block on line {}: {}", i + 1, line));
+        let (name, start, end, count) = parse_block(line).ok_or_else(invalid)?;
+        if end < start || end - start >= MAX_BLOCK_LINES {
+            return Err(invalid());
+        }
+        let hits = match mode {
+            Mode::Set => (count > 0) as u64,
+            Mode::Count => count,
+        };
+        let position = match index.get(name) {
+            Some(&p) => p,
+            None => {
+                index.insert(name.to_string(), files.len());
+                files.push(ParsedFile::new(map_path(name, mappings)));
+                files.len() - 1
+            },
+        };
+        let file = &mut files[position];
+        for l in start..=end {
+            let entry = file.lines.entry(l).or_insert(0);
+            *entry = (*entry).max(hits);
+        }
+    }
+    Ok(files)
+}
+
+/// Parses `name.go:startline.col,endline.col statements count`
+fn parse_block(line: &str) -> Option<(&str, usize, usize, u64)> {
+    let colon = line.rfind(':')?;
+    let (name, rest) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = rest.split_whitespace();
+    let range = parts.next()?;
+    let _statements = parts.next()?;
+    let count = parts.next()?.parse::<u64>().ok()?;
+    let mut bounds = range.split(',');
+    let start = bounds.next()?.split('.').next()?.parse::<usize>().ok()?;
+    let end = bounds.next()?.split('.').next()?.parse::<usize>().ok()?;
+    Some((name, start, end, count))
+}
+
+fn map_path(name: &str, mappings: &[(&str, &str)]) -> PathBuf {
+    let best = mappings.iter()
+                       .filter(|&&(from, _)| name == from || is_path_prefix(name, from))
+                       .max_by_key(|&&(from, _)| from.len());
+    match best {
+        Some(&(from, to)) => {
+            let rest = name[from.len()..].trim_start_matches('/');
+            PathBuf::from(to).join(rest)
+        },
+        None => PathBuf::from(name),
+    }
+}
+
+fn is_path_prefix(name: &str, prefix: &str) -> bool {
+    prefix.is_empty() ||
+        (name.starts_with(prefix) && (prefix.ends_with('/') || name[prefix.len()..].starts_with('/')))
+}
+
+
+/// `CoverageParser` for go coverage profiles
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct GoParser {
+    /// Pairs of (module path prefix, repo relative directory)
+    pub mappings: Vec<(String, String)>,
+}
+
+impl CoverageParser for GoParser {
+    fn parse(&self, input: &mut dyn Read, _: &ImportContext) -> Result<Vec<ParsedFile>, CoverallsError> {
+        let mappings = self.mappings.iter()
+                                    .map(|(from, to)| (from.as_str(), to.as_str()))
+                                    .collect::<Vec<_>>();
+        parse(BufReader::new(input), &mappings)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use import::golang::*;
+
+    const PROFILE: &str = "github.com/org/repo/services/api/handler.go:10.30,12.16 2 5
+github.com/org/repo/services/api/handler.go:12.16,14.3 1 0
+github.com/org/repo/services/api/handler.go:15.2,15.12 1 5
+github.com/org/repo/pkg/util/util.go:3.20,5.2 2 0
+";
+
+    #[test]
+    fn test_count_mode() {
+        let data = format!("mode: count\n{}", PROFILE);
+        let mappings = [("github.com/org/repo", ""), ("github.com/org/repo/services", "svc")];
+        let files = parse(data.as_bytes(), &mappings).unwrap();
+        assert_eq!(files.len(), 2);
+        
+        let handler = &files[0];
+        assert_eq!(handler.path, Path::new("svc/api/handler.go"));
+        assert_eq!(handler.lines.get(&10), Some(&5));
+        // Overlapping line between a hit and unhit block takes the max
+        assert_eq!(handler.lines.get(&12), Some(&5));
+        assert_eq!(handler.lines.get(&13), Some(&0));
+        assert_eq!(handler.lines.get(&15), Some(&5));
+        assert_eq!(handler.lines.get(&16), None);
+
+        assert_eq!(files[1].path, Path::new("pkg/util/util.go"));
+        assert_eq!(files[1].lines.get(&4), Some(&0));
+    }
+
+    #[test]
+    fn test_set_mode() {
+        let data = format!("mode: set\n{}", PROFILE);
+        let files = parse(data.as_bytes(), &[]).unwrap();
+        let handler = &files[0];
+        assert_eq!(handler.path, Path::new("github.com/org/repo/services/api/handler.go"));
+        assert_eq!(handler.lines.get(&10), Some(&1));
+        assert_eq!(handler.lines.get(&13), Some(&0));
+    }
+
+    #[test]
+    fn test_invalid_mode() {
+        assert!(parse("mode: fancy\n".as_bytes(), &[]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_ranges() {
+        for block in &["a.go:5.1,4.2 1 1", "a.go:1.1,4000000000.2 1 1", "a.go:1.1,18446744073709551615.2 1 1"] {
+            match parse(format!("mode: set\n{}\n", block).as_bytes(), &[]) {
+                Err(CoverallsError::Parse(ref e)) => assert!(e.contains(block), "{}", e),
+                r => panic!("Expected a parse error for {} got {:?}", block, r),
+            }
+        }
+        let files = parse("mode: set\na.go:3.1,3.9 1 1\n".as_bytes(), &[]).unwrap();
+        assert_eq!(files[0].lines.len(), 1);
+    }
+}
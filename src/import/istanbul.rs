@@ -0,0 +1,138 @@
+//! Parser for the `coverage-final.json` files written by Istanbul and nyc
+//! for JavaScript and TypeScript.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use serde_json;
+use import::{CoverageParser, ImportContext, ParsedFile};
+use {BranchData, CoverallsError};
+
+
+#[derive(Deserialize)]
+struct Position {
+    line: usize,
+}
+
+#[derive(Deserialize)]
+struct Location {
+    start: Position,
+}
+
+#[derive(Deserialize)]
+struct BranchMapping {
+    loc: Option<Location>,
+    line: Option<usize>,
+    #[serde(default)]
+    locations: Vec<Location>,
+}
+
+#[derive(Deserialize)]
+struct FileCoverage {
+    path: Option<String>,
+    #[serde(rename="statementMap", default)]
+    statement_map: HashMap<String, Location>,
+    #[serde(default)]
+    s: HashMap<String, u64>,
+    #[serde(rename="branchMap", default)]
+    branch_map: HashMap<String, BranchMapping>,
+    #[serde(default)]
+    b: HashMap<String, Vec<u64>>,
+}
+
+/// Parses an Istanbul `coverage-final.json`. File paths are left as they
+/// appear in the report (normally absolute), `CoverallsReport::add_parsed_files`
+/// resolves them against the repository root.
+///
+/// A line's hits are the maximum count of the statements starting on that
+/// line. Statements spanning multiple lines only count towards their first
+/// line, matching Istanbul's own line coverage. Each branch map entry becomes
+/// a block with one branch per path, on the line where the branch starts.
+/// Entries with no recorded paths are skipped.
+pub fn parse<R: Read>(reader: R) -> Result<Vec<ParsedFile>, CoverallsError> {
+    let report: BTreeMap<String, FileCoverage> = serde_json::from_reader(reader)?;
+    let mut result = Vec::new();
+    for (key, coverage) in report {
+        let mut file = ParsedFile::new(coverage.path.unwrap_or(key));
+        for (id, location) in &coverage.statement_map {
+            let count = coverage.s.get(id).cloned().unwrap_or(0);
+            let hits = file.lines.entry(location.start.line).or_insert(0);
+            *hits = (*hits).max(count);
+        }
+
+        let mut ids = coverage.branch_map.keys()
+                                         .filter_map(|k| k.parse::<usize>().ok().map(|n| (n, k)))
+                                         .collect::<Vec<_>>();
+        ids.sort();
+        let mut branches = Vec::new();
+        for (block, id) in ids {
+            let mapping = &coverage.branch_map[id];
+            let counts = match coverage.b.get(id) {
+                Some(c) if !c.is_empty() => c,
+                _ => continue,
+            };
+            let line = mapping.loc.as_ref().map(|l| l.start.line)
+                              .or(mapping.line)
+                              .or_else(|| mapping.locations.first().map(|l| l.start.line));
+            if let Some(line) = line {
+                for (i, hits) in counts.iter().enumerate() {
+                    branches.push(BranchData {
+                        line_number: line,
+                        block_name: block,
+                        branch_number: i,
+                        hits: *hits,
+                    });
+                }
+            }
+        }
+        if !branches.is_empty() {
+            file.branches = Some(branches);
+        }
+        result.push(file);
+    }
+    Ok(result)
+}
+
+
+/// `CoverageParser` for Istanbul `coverage-final.json` files
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct IstanbulParser;
+
+impl CoverageParser for IstanbulParser {
+    fn parse(&self, input: &mut dyn Read, _: &ImportContext) -> Result<Vec<ParsedFile>, CoverallsError> {
+        parse(input)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use import::istanbul::*;
+
+    #[test]
+    fn test_parse_report() {
+        let data = include_bytes!("../../tests/data/coverage-final.json");
+        let files = parse(&data[..]).unwrap();
+        assert_eq!(files.len(), 2);
+
+        let math = &files[0];
+        assert_eq!(math.path, Path::new("/home/ci/app/src/math.js"));
+        // Two statements start on line 2, the max count wins
+        assert_eq!(math.lines.get(&2), Some(&4));
+        // The statement spanning lines 3-6 is only counted on its first line
+        assert_eq!(math.lines.get(&3), Some(&4));
+        assert_eq!(math.lines.get(&4), None);
+        assert_eq!(math.lines.get(&7), Some(&0));
+
+        let branches = math.branches.as_ref().unwrap();
+        assert_eq!(branches, &vec![
+            BranchData { line_number: 3, block_name: 0, branch_number: 0, hits: 1 },
+            BranchData { line_number: 3, block_name: 0, branch_number: 1, hits: 3 },
+        ]);
+        
+        let empty = &files[1];
+        assert_eq!(empty.path, Path::new("/home/ci/app/src/util.js"));
+        assert_eq!(empty.lines.get(&1), Some(&1));
+        assert!(empty.branches.is_none());
+    }
+}
@@ -0,0 +1,130 @@
+//! Parser for JaCoCo XML reports used by JVM languages such as Java and
+//! Kotlin.
+
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use import::{numeric_attr, xml_attributes, CoverageParser, ImportContext, ParsedFile};
+use {BranchData, CoverallsError};
+
+
+/// Parses a JaCoCo XML report. Each `<sourcefile>` is named by joining its
+/// package name and file name under `source_root`, the repo relative
+/// directory containing the package tree (e.g. `src/main/kotlin`).
+///
+/// For `<line>` elements, covered instructions (`ci`) are used as the hit
+/// count, lines with only missed instructions get 0 hits. Branches are
+/// numbered in order on each line with a block per line containing branches.
+pub fn parse<R: BufRead>(reader: R, source_root: &Path) -> Result<Vec<ParsedFile>, CoverallsError> {
+    let mut reader = Reader::from_reader(reader);
+    let mut buf = Vec::new();
+    let mut result = Vec::new();
+    let mut package = PathBuf::new();
+    let mut current: Option<ParsedFile> = None;
+    let mut block = 0;
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                match e.local_name().as_ref() {
+                    b"package" => {
+                        let attrs = xml_attributes(e)?;
+                        package = attrs.get("name").map(PathBuf::from).unwrap_or_default();
+                    },
+                    b"sourcefile" => {
+                        let attrs = xml_attributes(e)?;
+                        let name = attrs.get("name").ok_or_else(|| {
+                            CoverallsError::Parse("sourcefile without a name".to_string())
+                        })?;
+                        current = Some(ParsedFile::new(source_root.join(&package).join(name)));
+                        block = 0;
+                    },
+                    b"line" => if let Some(ref mut file) = current {
+                        let attrs = xml_attributes(e)?;
+                        let line = numeric_attr(&attrs, "nr")?;
+                        let mi: u64 = numeric_attr(&attrs, "mi")?;
+                        let ci: u64 = numeric_attr(&attrs, "ci")?;
+                        let mb: usize = numeric_attr(&attrs, "mb")?;
+                        let cb: usize = numeric_attr(&attrs, "cb")?;
+                        if ci > 0 || mi > 0 {
+                            file.lines.insert(line, ci);
+                        }
+                        if mb + cb > 0 {
+                            let branches = file.branches.get_or_insert_with(Vec::new);
+                            for i in 0..(mb + cb) {
+                                branches.push(BranchData {
+                                    line_number: line,
+                                    block_name: block,
+                                    branch_number: i,
+                                    hits: (i < cb) as u64,
+                                });
+                            }
+                            block += 1;
+                        }
+                    },
+                    _ => {},
+                }
+            },
+            Event::End(ref e) => {
+                match e.local_name().as_ref() {
+                    b"sourcefile" => if let Some(file) = current.take() {
+                        result.push(file);
+                    },
+                    b"package" => package = PathBuf::new(),
+                    _ => {},
+                }
+            },
+            Event::Eof => break,
+            _ => {},
+        }
+        buf.clear();
+    }
+    Ok(result)
+}
+
+
+/// `CoverageParser` for JaCoCo XML reports
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct JacocoParser {
+    /// Repo relative directory containing the package tree
+    pub source_root: PathBuf,
+}
+
+impl CoverageParser for JacocoParser {
+    fn parse(&self, input: &mut dyn Read, _: &ImportContext) -> Result<Vec<ParsedFile>, CoverallsError> {
+        parse(BufReader::new(input), &self.source_root)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use import::jacoco::*;
+
+    #[test]
+    fn test_parse_report() {
+        let data = include_bytes!("../../tests/data/jacoco.xml");
+        let files = parse(&data[..], Path::new("services/api/src/main/kotlin")).unwrap();
+        assert_eq!(files.len(), 2);
+
+        let greeter = &files[0];
+        assert_eq!(greeter.path, Path::new("services/api/src/main/kotlin/com/example/api/Greeter.kt"));
+        assert_eq!(greeter.lines.get(&3), Some(&3));
+        assert_eq!(greeter.lines.get(&5), Some(&4));
+        assert_eq!(greeter.lines.get(&6), Some(&2));
+        assert_eq!(greeter.lines.get(&7), Some(&0));
+        assert_eq!(greeter.lines.get(&4), None);
+
+        // Line 5 is partially covered, one of the two branches was taken
+        let branches = greeter.branches.as_ref().unwrap();
+        assert_eq!(branches.len(), 2);
+        assert_eq!(branches[0], BranchData { line_number: 5, block_name: 0, branch_number: 0, hits: 1 });
+        assert_eq!(branches[1], BranchData { line_number: 5, block_name: 0, branch_number: 1, hits: 0 });
+
+        let util = &files[1];
+        assert_eq!(util.path, Path::new("services/api/src/main/kotlin/com/example/util/Strings.kt"));
+        assert_eq!(util.lines.get(&2), Some(&0));
+        assert!(util.branches.is_none());
+    }
+}
@@ -0,0 +1,115 @@
+//! Parser for LCOV tracefiles as produced by lcov, grcov and many others.
+
+use std::io::{BufRead, BufReader, Read};
+use import::{merge_duplicates, CoverageParser, ImportContext, ParsedFile};
+use {BranchData, CoverallsError};
+
+
+/// Parses an LCOV tracefile. Only the `SF`, `DA` and `BRDA` records are used,
+/// a `-` hit count on a branch (the block was never executed) is read as 0.
+/// Records for the same file are merged.
+pub fn parse<R: BufRead>(reader: R) -> Result<Vec<ParsedFile>, CoverallsError> {
+    let mut files = Vec::new();
+    let mut current: Option<ParsedFile> = None;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        let invalid = || CoverallsError::Parse(format!("Invalid record on line {}: {}", i + 1, line));
+        if let Some(path) = line.strip_prefix("SF:") {
+            current = Some(ParsedFile::new(path));
+        } else if line == "end_of_record" {
+            files.extend(current.take());
+        } else if let Some(ref mut file) = current {
+            if let Some(record) = line.strip_prefix("DA:") {
+                let values = numbers(record, 2).ok_or_else(invalid)?;
+                if values.len() < 2 {
+                    return Err(invalid());
+                }
+                let hits = file.lines.entry(values[0] as usize).or_insert(0);
+                *hits = hits.saturating_add(values[1]);
+            } else if let Some(record) = line.strip_prefix("BRDA:") {
+                let values = numbers(record, 4).ok_or_else(invalid)?;
+                if values.len() != 4 {
+                    return Err(invalid());
+                }
+                file.branches.get_or_insert_with(Vec::new).push(BranchData {
+                    line_number: values[0] as usize,
+                    block_name: values[1] as usize,
+                    branch_number: values[2] as usize,
+                    hits: values[3],
+                });
+            }
+        }
+    }
+    files.extend(current.take());
+    Ok(merge_duplicates(files))
+}
+
+/// Parses the first `fields` of a comma separated list of numbers, a `-` is
+/// read as 0. Anything after them, such as the checksum `lcov --checksum`
+/// adds to `DA` records, is ignored.
+fn numbers(record: &str, fields: usize) -> Option<Vec<u64>> {
+    record.split(',')
+          .take(fields)
+          .map(|v| match v.trim() {
+              "-" => Some(0),
+              v => v.parse::<u64>().ok(),
+          })
+          .collect()
+}
+
+/// `CoverageParser` for LCOV tracefiles
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct LcovParser;
+
+impl CoverageParser for LcovParser {
+    fn parse(&self, input: &mut dyn Read, _: &ImportContext) -> Result<Vec<ParsedFile>, CoverallsError> {
+        parse(BufReader::new(input))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use import::lcov::*;
+
+    #[test]
+    fn test_parse() {
+        let data = "TN:
+SF:/repo/src/lib.rs
+FN:3,main
+DA:3,1
+DA:4,0
+BRDA:4,0,0,1
+BRDA:4,0,1,-
+LF:2
+LH:1
+end_of_record
+SF:/repo/src/lib.rs
+DA:4,2
+end_of_record
+";
+        let files = parse(data.as_bytes()).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, Path::new("/repo/src/lib.rs"));
+        assert_eq!(files[0].lines.get(&3), Some(&1));
+        assert_eq!(files[0].lines.get(&4), Some(&2));
+        assert_eq!(files[0].branches, Some(vec![
+            BranchData { line_number: 4, block_name: 0, branch_number: 0, hits: 1 },
+            BranchData { line_number: 4, block_name: 0, branch_number: 1, hits: 0 },
+        ]));
+    }
+
+    #[test]
+    fn test_invalid_record() {
+        assert!(parse("SF:a.rs\nDA:x,1\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_checksummed_lines() {
+        let files = parse("SF:a.rs\nDA:1,1,XyZ+abc==\nDA:2,0,Qm9v\nend_of_record\n".as_bytes()).unwrap();
+        assert_eq!(files[0].lines.get(&1), Some(&1));
+        assert_eq!(files[0].lines.get(&2), Some(&0));
+    }
+}
@@ -0,0 +1,332 @@
+//! Importers for coverage formats produced by other tools. Each importer
+//! produces a list of `ParsedFile`s which can then be turned into coveralls
+//! sources via `CoverallsReport::add_parsed_files`, or more conveniently
+//! through the `CoverageParser` trait and `CoverallsReport::import`.
+//!
+//! Formats not supported by the crate can be added by implementing
+//! `CoverageParser`.
+
+pub mod clover;
+pub mod gcov;
+pub mod golang;
+pub mod istanbul;
+pub mod jacoco;
+pub mod lcov;
+pub mod opencover;
+pub mod remap;
+pub mod simplecov;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use glob::Pattern;
+use quick_xml::events::BytesStart;
+use {BranchData, CoverallsError};
+use ignore::IgnoreOptions;
+use self::remap::PathRemapper;
+
+
+/// A parser for a coverage format
+pub trait CoverageParser {
+    /// Parses the coverage in `input` into per-file coverage. Paths may be
+    /// absolute or relative to the repository root.
+    fn parse(&self, input: &mut dyn Read, ctx: &ImportContext) -> Result<Vec<ParsedFile>, CoverallsError>;
+}
+
+/// Settings shared by all imports
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct ImportContext {
+    /// Root of the repository, parsed paths are made relative to this
+    pub root: PathBuf,
+    /// Path prefix rules applied to parsed paths before they are made
+    /// relative to the root
+    pub remappings: PathRemapper,
+    /// Glob patterns for repo relative paths which shouldn't be imported
+    pub exclude: Vec<String>,
+    /// How parsed paths and the root are resolved before comparing them
+    pub path_policy: PathPolicy,
+    /// Ignore files and patterns whose matches aren't imported, by default
+    /// none are read
+    pub ignore: IgnoreOptions,
+}
+
+/// Whether symlinks and `..` components are resolved before a repository
+/// relative name is computed
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct PathPolicy {
+    /// Resolve symlinks, and `..`, through the filesystem. Paths which can't
+    /// be canonicalized, e.g. dangling symlinks, are normalized lexically
+    /// instead and counted in `ImportStats::paths_normalized_lexically`.
+    pub canonicalize: bool,
+    /// Remove `.` and `..` components without touching the filesystem
+    pub resolve_dotdot: bool,
+}
+
+impl Default for PathPolicy {
+    fn default() -> Self {
+        PathPolicy {
+            canonicalize: true,
+            resolve_dotdot: true,
+        }
+    }
+}
+
+impl PathPolicy {
+    /// Leaves paths exactly as given
+    pub fn unchanged() -> Self {
+        PathPolicy {
+            canonicalize: false,
+            resolve_dotdot: false,
+        }
+    }
+
+    /// Resolves `path` according to the policy. Relative paths are only ever
+    /// normalized lexically as they may not be relative to the working
+    /// directory.
+    pub fn apply(&self, path: &Path) -> PathBuf {
+        self.apply_counting(path, &mut 0)
+    }
+
+    /// As `apply`, adding one to `fallbacks` if the path couldn't be
+    /// canonicalized and was normalized lexically instead
+    pub fn apply_counting(&self, path: &Path, fallbacks: &mut usize) -> PathBuf {
+        if self.canonicalize && path.is_absolute() {
+            match path.canonicalize() {
+                Ok(p) => return p,
+                Err(_) => {
+                    *fallbacks += 1;
+                    return lexical_normalize(path);
+                },
+            }
+        }
+        if self.canonicalize || self.resolve_dotdot {
+            lexical_normalize(path)
+        } else {
+            path.to_path_buf()
+        }
+    }
+}
+
+/// Removes `.` components and resolves `..` against the preceding component
+/// without accessing the filesystem. Leading `..` of relative paths are kept.
+pub fn lexical_normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => match result.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    result.pop();
+                },
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {},
+                _ => result.push(".."),
+            },
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+impl ImportContext {
+    /// Creates a context for a repository with no remappings or exclusions
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        ImportContext {
+            root: root.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Applies the remapping rules to the path
+    pub fn remap(&self, path: &Path) -> PathBuf {
+        self.remappings.remap(path)
+    }
+
+    /// Makes the path relative to the root, after resolving both according
+    /// to the path policy. Returns None for absolute paths outside of the
+    /// root, including Windows drive letter and UNC paths on other
+    /// platforms.
+    pub fn relativize(&self, path: &Path) -> Option<PathBuf> {
+        self.relativize_counting(path, &mut 0)
+    }
+
+    /// As `relativize`, counting paths normalized lexically in `fallbacks` as
+    /// `PathPolicy::apply_counting` does
+    pub(crate) fn relativize_counting(&self, path: &Path, fallbacks: &mut usize) -> Option<PathBuf> {
+        let path = self.path_policy.apply_counting(path, fallbacks);
+        if is_windows_absolute(&path.to_string_lossy()) && !path.is_absolute() {
+            None
+        } else if path.is_absolute() {
+            let root = self.path_policy.apply(&self.root);
+            path.strip_prefix(&root).ok().map(|p| p.to_path_buf())
+        } else {
+            Some(path)
+        }
+    }
+
+    /// Compiles the exclusion globs
+    pub(crate) fn exclusion_patterns(&self) -> Result<Vec<Pattern>, CoverallsError> {
+        let patterns = self.exclude.iter()
+                                   .map(|p| Pattern::new(p))
+                                   .collect::<Result<Vec<Pattern>, _>>()?;
+        Ok(patterns)
+    }
+}
+
+
+/// Coverage for a single file read from a foreign format
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct ParsedFile {
+    /// Path of the source file, relative to the repository root or absolute
+    pub path: PathBuf,
+    /// Map of line numbers to hits
+    pub lines: HashMap<usize, u64>,
+    /// Branch data, if present in the format
+    pub branches: Option<Vec<BranchData>>,
+    /// The file no longer exists, so its source is built from `lines` alone
+    /// with no digest rather than by reading it
+    pub missing: bool,
+}
+
+impl ParsedFile {
+    /// Creates an empty parsed file for the given repo relative path
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        ParsedFile {
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Merges another parsed file for the same source into this one, summing
+    /// line and branch hits. Sums saturate at `u64::MAX`.
+    pub fn merge(&mut self, other: &ParsedFile) {
+        self.missing |= other.missing;
+        for (line, hits) in &other.lines {
+            let entry = self.lines.entry(*line).or_insert(0);
+            *entry = entry.saturating_add(*hits);
+        }
+        if let Some(ref theirs) = other.branches {
+            let mine = self.branches.get_or_insert_with(Vec::new);
+            for b in theirs {
+                match mine.iter_mut().find(|x| x.line_number == b.line_number &&
+                                           x.block_name == b.block_name &&
+                                           x.branch_number == b.branch_number) {
+                    Some(x) => x.hits = x.hits.saturating_add(b.hits),
+                    None => mine.push(*b),
+                }
+            }
+        }
+    }
+}
+
+/// Combines parsed files sharing a path, preserving the order in which each
+/// path was first seen.
+pub fn merge_duplicates(files: Vec<ParsedFile>) -> Vec<ParsedFile> {
+    let mut result: Vec<ParsedFile> = Vec::new();
+    let mut index: HashMap<PathBuf, usize> = HashMap::new();
+    for file in files {
+        if let Some(&i) = index.get(&file.path) {
+            result[i].merge(&file);
+        } else {
+            index.insert(file.path.clone(), result.len());
+            result.push(file);
+        }
+    }
+    result
+}
+
+/// Removes `prefix` from the start of `path` if present otherwise returns the
+/// path unchanged.
+pub fn strip_prefix(path: &Path, prefix: &Path) -> PathBuf {
+    match path.strip_prefix(prefix) {
+        Ok(p) => p.to_path_buf(),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// Recursively finds all files under `dir` with the given extension, resolved
+/// by `policy`. Results are sorted and deduplicated so imports are
+/// deterministic.
+pub(crate) fn find_files(dir: &Path, extension: &str, policy: &PathPolicy) -> io::Result<Vec<PathBuf>> {
+    let mut result = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(d) = pending.pop() {
+        for entry in fs::read_dir(&d)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().map(|e| e == extension).unwrap_or(false) {
+                result.push(policy.apply(&path));
+            }
+        }
+    }
+    result.sort();
+    result.dedup();
+    Ok(result)
+}
+
+/// Collects the attributes of an XML element into a map of name to value
+pub(crate) fn xml_attributes(e: &BytesStart) -> Result<HashMap<String, String>, CoverallsError> {
+    let mut result = HashMap::new();
+    for attr in e.attributes() {
+        let attr = attr.map_err(::quick_xml::Error::from)?;
+        let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+        result.insert(key, attr.unescape_value()?.into_owned());
+    }
+    Ok(result)
+}
+
+/// Reads a numeric attribute, a missing attribute is treated as 0.
+pub(crate) fn numeric_attr<T: FromStr + Default>(attrs: &HashMap<String, String>, key: &str) -> Result<T, CoverallsError> {
+    match attrs.get(key) {
+        Some(v) => v.trim().parse::<T>().map_err(|_| {
+            CoverallsError::Parse(format!("Invalid value for {}: {}", key, v))
+        }),
+        None => Ok(T::default()),
+    }
+}
+
+/// Converts a path which may have come from a Windows machine into forward
+/// slash form, removing any `\\?\` extended length prefix.
+pub(crate) fn normalize_separators(path: &str) -> String {
+    let path = path.strip_prefix(r"\\?\").unwrap_or(path);
+    path.replace('\\', "/")
+}
+
+/// Whether `path` is a Windows path with a drive letter or server, such as
+/// `D:/shared/a.cs` or `\\server\share\a.cs`, whatever the platform
+pub(crate) fn is_windows_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    let drive = bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' &&
+        (bytes[2] == b'/' || bytes[2] == b'\\');
+    drive || path.starts_with("//") || path.starts_with(r"\\")
+}
+
+/// Normalizes a source name to the forward slash form coveralls expects,
+/// also dropping any drive letter left over from canonicalizing on Windows.
+pub(crate) fn normalize_name(name: &str) -> String {
+    let name = normalize_separators(name);
+    let bytes = name.as_bytes();
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        name[2..].trim_start_matches('/').to_string()
+    } else {
+        name
+    }
+}
+
+/// Strips `root` from the start of `path` comparing case-insensitively as
+/// Windows does. Both should already be in forward slash form. Returns None
+/// if the path isn't under the root.
+pub(crate) fn strip_root_ignore_case<'a>(path: &'a str, root: &str) -> Option<&'a str> {
+    let root = root.trim_end_matches('/');
+    if path.len() < root.len() || !path.is_char_boundary(root.len()) {
+        return None;
+    }
+    let (start, rest) = path.split_at(root.len());
+    if start.eq_ignore_ascii_case(root) && (root.is_empty() || rest.is_empty() || rest.starts_with('/')) {
+        Some(rest.trim_start_matches('/'))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,185 @@
+//! Parser for OpenCover XML, as produced by OpenCover itself and by Coverlet
+//! for .NET projects.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use quick_xml::Reader;
+use quick_xml::events::Event;
+use import::{merge_duplicates, normalize_separators, numeric_attr, strip_root_ignore_case, xml_attributes,
+             CoverageParser, ImportContext, ParsedFile};
+use {BranchData, CoverallsError};
+
+
+/// Parses an OpenCover report. File uids are resolved per module to the full
+/// paths in `<File>` elements. Those are normalised to forward slashes and
+/// made relative by removing `root`, the directory the build ran in in the
+/// same format as the report, e.g. `C:\agent\_work\1\s`. The comparison is
+/// case insensitive. Paths outside the root are returned normalised but
+/// otherwise unchanged.
+///
+/// Sequence points give hits to every line they span, taking the max for
+/// lines in multiple sequence points. Branch points become branches with the
+/// IL offset as block and the path as the branch number.
+pub fn parse<R: BufRead>(reader: R, root: &str) -> Result<Vec<ParsedFile>, CoverallsError> {
+    let root = normalize_separators(root);
+    let mut reader = Reader::from_reader(reader);
+    let mut buf = Vec::new();
+    // Uids are only unique within a module
+    let mut paths: HashMap<usize, String> = HashMap::new();
+    let mut files: HashMap<usize, ParsedFile> = HashMap::new();
+    let mut result = Vec::new();
+    let mut method_file: Option<usize> = None;
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                match e.local_name().as_ref() {
+                    b"File" => {
+                        let attrs = xml_attributes(e)?;
+                        let uid = numeric_attr(&attrs, "uid")?;
+                        if let Some(path) = attrs.get("fullPath") {
+                            let path = normalize_separators(path);
+                            let name = match strip_root_ignore_case(&path, &root) {
+                                Some(relative) => relative.to_string(),
+                                None => path.clone(),
+                            };
+                            paths.insert(uid, name);
+                        }
+                    },
+                    b"FileRef" => {
+                        let attrs = xml_attributes(e)?;
+                        method_file = Some(numeric_attr(&attrs, "uid")?);
+                    },
+                    b"SequencePoint" => {
+                        let attrs = xml_attributes(e)?;
+                        if let Some(file) = point_file(&attrs, method_file, &paths, &mut files)? {
+                            let start: usize = numeric_attr(&attrs, "sl")?;
+                            let end = numeric_attr::<usize>(&attrs, "el")?.max(start);
+                            let hits = numeric_attr(&attrs, "vc")?;
+                            for line in start..(end + 1) {
+                                let entry = file.lines.entry(line).or_insert(0);
+                                *entry = (*entry).max(hits);
+                            }
+                        }
+                    },
+                    b"BranchPoint" => {
+                        let attrs = xml_attributes(e)?;
+                        if let Some(file) = point_file(&attrs, method_file, &paths, &mut files)? {
+                            let branch = BranchData {
+                                line_number: numeric_attr(&attrs, "sl")?,
+                                block_name: numeric_attr(&attrs, "offset")?,
+                                branch_number: numeric_attr(&attrs, "path")?,
+                                hits: numeric_attr(&attrs, "vc")?,
+                            };
+                            file.branches.get_or_insert_with(Vec::new).push(branch);
+                        }
+                    },
+                    _ => {},
+                }
+            },
+            Event::End(ref e) => {
+                match e.local_name().as_ref() {
+                    b"Method" => method_file = None,
+                    b"Module" => {
+                        let mut uids = files.keys().cloned().collect::<Vec<_>>();
+                        uids.sort();
+                        for uid in uids {
+                            result.extend(files.remove(&uid));
+                        }
+                        paths.clear();
+                    },
+                    _ => {},
+                }
+            },
+            Event::Eof => break,
+            _ => {},
+        }
+        buf.clear();
+    }
+    Ok(merge_duplicates(result))
+}
+
+/// Finds the file a sequence or branch point belongs to, using its `fileid`
+/// if present otherwise the enclosing method's `FileRef`.
+fn point_file<'a>(attrs: &HashMap<String, String>,
+                  method_file: Option<usize>,
+                  paths: &HashMap<usize, String>,
+                  files: &'a mut HashMap<usize, ParsedFile>) -> Result<Option<&'a mut ParsedFile>, CoverallsError> {
+    let uid = if attrs.contains_key("fileid") {
+        numeric_attr(attrs, "fileid")?
+    } else {
+        match method_file {
+            Some(uid) => uid,
+            None => return Ok(None),
+        }
+    };
+    match paths.get(&uid) {
+        Some(path) => Ok(Some(files.entry(uid).or_insert_with(|| ParsedFile::new(path.as_str())))),
+        None => Ok(None),
+    }
+}
+
+
+/// `CoverageParser` for OpenCover XML reports
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct OpenCoverParser {
+    /// Directory the build ran in, as it appears in the report
+    pub root: String,
+}
+
+impl CoverageParser for OpenCoverParser {
+    fn parse(&self, input: &mut dyn Read, _: &ImportContext) -> Result<Vec<ParsedFile>, CoverallsError> {
+        parse(BufReader::new(input), &self.root)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use tempfile::TempDir;
+    use import::opencover::*;
+    use {CoverallsReport, Identity, ImportStats};
+
+    #[test]
+    fn test_parse_report() {
+        let data = include_bytes!("../../tests/data/opencover.xml");
+        let files = parse(&data[..], r"c:\agent\_work\1\s").unwrap();
+        assert_eq!(files.len(), 2);
+
+        let calc = &files[0];
+        assert_eq!(calc.path, Path::new("src/Lib/Calculator.cs"));
+        assert_eq!(calc.lines.get(&10), Some(&4));
+        assert_eq!(calc.lines.get(&11), Some(&4));
+        // Line 12 is in sequence points from two methods, max is used
+        assert_eq!(calc.lines.get(&12), Some(&3));
+        assert_eq!(calc.lines.get(&13), Some(&0));
+        assert_eq!(calc.lines.get(&14), None);
+
+        let branches = calc.branches.as_ref().unwrap();
+        assert_eq!(branches, &vec![
+            BranchData { line_number: 12, block_name: 7, branch_number: 0, hits: 3 },
+            BranchData { line_number: 12, block_name: 7, branch_number: 1, hits: 0 },
+        ]);
+
+        let other = &files[1];
+        assert_eq!(other.path, Path::new("D:/shared/Generated.cs"));
+        assert_eq!(other.lines.get(&3), Some(&1));
+    }
+
+    #[test]
+    fn test_import_outside_root() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("src/Lib")).unwrap();
+        fs::write(dir.path().join("src/Lib/Calculator.cs"), "\n".repeat(20)).unwrap();
+        let parser = OpenCoverParser { root: r"c:\agent\_work\1\s".to_string() };
+        let data = include_bytes!("../../tests/data/opencover.xml");
+        let mut report = CoverallsReport::new(Identity::RepoToken("token".into()));
+        let stats = report.import(&parser, &data[..], &ImportContext::new(dir.path())).unwrap();
+        assert_eq!(stats, ImportStats { files_added: 1, files_skipped: 1, ..Default::default() });
+
+        let ctx = ImportContext::new("/repo");
+        assert_eq!(ctx.relativize(Path::new(r"\\server\share\a.cs")), None);
+        assert_eq!(ctx.relativize(Path::new("src/a.cs")), Some(PathBuf::from("src/a.cs")));
+    }
+}
@@ -0,0 +1,130 @@
+//! Rewriting of path prefixes, e.g. for coverage collected in a container
+//! where the repository is mounted somewhere other than on the host.
+
+use std::env::var;
+use std::path::{Path, PathBuf};
+use import::normalize_separators;
+use CoverallsError;
+
+
+/// Environment variable holding remapping rules as `from=>to` pairs
+/// separated by `;`, e.g. `COVERALLS_PATH_MAPPING="/build/src=>"`
+pub const PATH_MAPPING_VAR: &str = "COVERALLS_PATH_MAPPING";
+
+/// Ordered list of (from, to) path prefix rules. The longest matching from
+/// prefix wins, with rules of equal length tried in the order they were
+/// added. Windows separators are normalized before matching.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct PathRemapper {
+    rules: Vec<(String, String)>,
+}
+
+impl PathRemapper {
+    /// Creates a remapper with no rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses rules in the `COVERALLS_PATH_MAPPING` format
+    pub fn parse(spec: &str) -> Result<Self, CoverallsError> {
+        let mut remapper = Self::new();
+        for rule in spec.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+            match rule.split_once("=>") {
+                Some((from, to)) => remapper.add_rule(from.trim(), to.trim()),
+                None => return Err(CoverallsError::Parse(format!("Invalid path mapping: {}", rule))),
+            }
+        }
+        Ok(remapper)
+    }
+
+    /// Reads rules from `COVERALLS_PATH_MAPPING`, no rules if it isn't set
+    pub fn from_env() -> Result<Self, CoverallsError> {
+        match var(PATH_MAPPING_VAR) {
+            Ok(spec) => Self::parse(&spec),
+            Err(_) => Ok(Self::new()),
+        }
+    }
+
+    /// Adds a rule replacing the `from` prefix with `to`. An empty `to`
+    /// leaves the remainder as a relative path. A `from` of `/` matches every
+    /// absolute path, an empty `from` matches nothing.
+    pub fn add_rule<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) {
+        let from = normalize_separators(&from.as_ref().to_string_lossy());
+        let to = normalize_separators(&to.as_ref().to_string_lossy());
+        let from = match from.trim_end_matches('/') {
+            "" if from.starts_with('/') => "/",
+            trimmed => trimmed,
+        };
+        self.rules.push((from.to_string(), to));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Applies the longest matching rule, paths matching no rule are
+    /// returned with only their separators normalized
+    pub fn remap(&self, path: &Path) -> PathBuf {
+        let path = normalize_separators(&path.to_string_lossy());
+        let best = self.rules.iter()
+            .filter(|(from, _)| !from.is_empty())
+            .filter_map(|(from, to)| {
+                let rest = path.strip_prefix(from.as_str())?;
+                if rest.is_empty() || rest.starts_with('/') || from == "/" {
+                    Some((from.len(), to, rest.trim_start_matches('/')))
+                } else {
+                    None
+                }
+            })
+            .fold(None, |best: Option<(usize, &String, &str)>, m| match best {
+                Some(b) if b.0 >= m.0 => Some(b),
+                _ => Some(m),
+            });
+        match best {
+            Some((_, to, rest)) if to.is_empty() => PathBuf::from(rest),
+            Some((_, to, rest)) => Path::new(to).join(rest),
+            None => PathBuf::from(path),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use import::remap::*;
+
+    #[test]
+    fn test_longest_prefix() {
+        let mut remapper = PathRemapper::new();
+        remapper.add_rule("/build", "/other");
+        remapper.add_rule("/build/src/", "");
+        remapper.add_rule(r"C:\agent\work", "/repo");
+        assert_eq!(remapper.remap(Path::new("/build/src/lib.rs")), PathBuf::from("lib.rs"));
+        assert_eq!(remapper.remap(Path::new("/build/target/gen.rs")), PathBuf::from("/other/target/gen.rs"));
+        assert_eq!(remapper.remap(Path::new(r"C:\agent\work\src\lib.rs")), PathBuf::from("/repo/src/lib.rs"));
+        // Prefixes only match whole components
+        assert_eq!(remapper.remap(Path::new("/builder/lib.rs")), PathBuf::from("/builder/lib.rs"));
+        assert_eq!(remapper.remap(Path::new("src/lib.rs")), PathBuf::from("src/lib.rs"));
+    }
+
+    #[test]
+    fn test_root_rule() {
+        let mut remapper = PathRemapper::new();
+        remapper.add_rule("/", "/abs/");
+        remapper.add_rule("", "/never");
+        assert_eq!(remapper.remap(Path::new("/build/lib.rs")), PathBuf::from("/abs/build/lib.rs"));
+        assert_eq!(remapper.remap(Path::new("src/lib.rs")), PathBuf::from("src/lib.rs"));
+        remapper.add_rule("/build", "");
+        assert_eq!(remapper.remap(Path::new("/build/lib.rs")), PathBuf::from("lib.rs"));
+    }
+
+    #[test]
+    fn test_parse() {
+        let remapper = PathRemapper::parse("/build/src=>; /mnt/a => /home/a").unwrap();
+        assert_eq!(remapper.remap(Path::new("/build/src/main.rs")), PathBuf::from("main.rs"));
+        assert_eq!(remapper.remap(Path::new("/mnt/a/b.rs")), PathBuf::from("/home/a/b.rs"));
+        assert!(PathRemapper::parse("").unwrap().is_empty());
+        assert!(PathRemapper::parse("/build/src").is_err());
+    }
+}
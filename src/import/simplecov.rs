@@ -0,0 +1,128 @@
+//! Parser for the `.resultset.json` files written by SimpleCov for Ruby.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+use serde_json::{self, Value};
+use import::{merge_duplicates, CoverageParser, ImportContext, ParsedFile};
+use CoverallsError;
+
+
+#[derive(Deserialize)]
+struct Suite {
+    #[serde(default)]
+    coverage: BTreeMap<String, Value>,
+}
+
+/// Parses a SimpleCov resultset. Both the current layout, where each file
+/// maps to `{"lines": [...]}`, and the legacy layout of a bare array are
+/// supported. Results from multiple suites (e.g. RSpec and Minitest) are
+/// merged by summing the hits.
+///
+/// Paths are left as they appear in the resultset, normally absolute. If
+/// `skip_missing` is set files that don't exist (relative paths are checked
+/// under `root`) are dropped, otherwise they are returned marked `missing`
+/// so their sources are built from the recorded lines with no digest.
+pub fn parse<R: Read>(reader: R, root: &Path, skip_missing: bool) -> Result<Vec<ParsedFile>, CoverallsError> {
+    let suites: BTreeMap<String, Suite> = serde_json::from_reader(reader)?;
+    let mut files = Vec::new();
+    for suite in suites.values() {
+        for (path, coverage) in &suite.coverage {
+            let lines = match *coverage {
+                Value::Array(ref l) => l,
+                Value::Object(ref o) => match o.get("lines") {
+                    Some(Value::Array(l)) => l,
+                    _ => continue,
+                },
+                _ => {
+                    return Err(CoverallsError::Parse(format!("Unexpected coverage for {}", path)));
+                },
+            };
+            let missing = !root.join(path).exists();
+            if skip_missing && missing {
+                continue;
+            }
+            let mut file = ParsedFile::new(path.as_str());
+            file.missing = missing;
+            // Anything other than a count (null, or "ignored" for nocov
+            // sections) isn't relevant.
+            for (i, hits) in lines.iter().enumerate() {
+                if let Some(h) = hits.as_u64() {
+                    file.lines.insert(i + 1, h);
+                }
+            }
+            files.push(file);
+        }
+    }
+    Ok(merge_duplicates(files))
+}
+
+
+/// `CoverageParser` for SimpleCov resultsets, paths are checked for
+/// existence under the import root
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct SimpleCovParser {
+    /// Drop files which no longer exist instead of importing their coverage
+    /// without a digest
+    pub skip_missing: bool,
+}
+
+impl CoverageParser for SimpleCovParser {
+    fn parse(&self, input: &mut dyn Read, ctx: &ImportContext) -> Result<Vec<ParsedFile>, CoverallsError> {
+        parse(input, &ctx.root, self.skip_missing)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::path::Path;
+    use import::simplecov::*;
+    use {CoverallsReport, Identity};
+
+    #[test]
+    fn test_current_layout() {
+        let data = include_bytes!("../../tests/data/simplecov/resultset.json");
+        let files = parse(&data[..], Path::new("/"), false).unwrap();
+        assert_eq!(files.len(), 2);
+
+        let user = files.iter().find(|f| f.path == Path::new("/home/ci/app/models/user.rb")).unwrap();
+        // Summed across the RSpec and Minitest suites
+        assert_eq!(user.lines.get(&1), None);
+        assert_eq!(user.lines.get(&2), Some(&3));
+        assert_eq!(user.lines.get(&3), Some(&0));
+        assert_eq!(user.lines.get(&4), Some(&2));
+        assert_eq!(user.lines.get(&5), None);
+    }
+
+    #[test]
+    fn test_legacy_layout() {
+        let data = include_bytes!("../../tests/data/simplecov/legacy_resultset.json");
+        let files = parse(&data[..], Path::new("/"), false).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].lines.get(&1), Some(&1));
+        assert_eq!(files[0].lines.get(&2), Some(&0));
+        assert_eq!(files[0].lines.get(&3), None);
+    }
+
+    #[test]
+    fn test_skip_missing() {
+        let data = r#"{"RSpec": {"coverage": {"tests/example/mysource.rs": {"lines": [1]},
+                                              "tests/example/deleted.rb": {"lines": [1]}}}}"#;
+        let root = env::current_dir().unwrap();
+        let files = parse(data.as_bytes(), &root, true).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, Path::new("tests/example/mysource.rs"));
+        let files = parse(data.as_bytes(), &root, false).unwrap();
+        assert_eq!(files.len(), 2);
+        assert!(files[0].missing && !files[1].missing);
+
+        let mut report = CoverallsReport::new(Identity::RepoToken("token".into()));
+        let stats = report.import(&SimpleCovParser::default(), data.as_bytes(), &ImportContext::new(&root)).unwrap();
+        assert_eq!(stats.files_added, 2);
+        let deleted = report.source_files.iter().find(|s| s.name() == "tests/example/deleted.rb").unwrap();
+        assert_eq!(deleted.source_digest, "");
+        assert_eq!(deleted.coverage().collect::<Vec<_>>(), [Some(1)]);
+    }
+}
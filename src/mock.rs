@@ -0,0 +1,140 @@
+//! A local stand-in for the coveralls API so uploads can be tested end to
+//! end without the network. Each connection gets the next canned response
+//! and the request it sent is recorded.
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::thread;
+
+
+/// A request received by the mock server
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub(crate) struct CapturedRequest {
+    /// Request line and headers, without the blank line ending them
+    pub headers: String,
+    /// Body exactly as received
+    pub body: Vec<u8>,
+}
+
+/// A part of a `multipart/form-data` body
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub(crate) struct FormPart {
+    /// Headers of the part such as its `Content-Disposition`
+    pub headers: String,
+    pub data: Vec<u8>,
+}
+
+impl CapturedRequest {
+    /// Value of a header, matched case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.lines()
+            .filter_map(|l| l.split_once(':'))
+            .find(|&(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.trim())
+    }
+
+    /// The form field called `name` if the body is multipart form data
+    pub fn form_part(&self, name: &str) -> Option<FormPart> {
+        let boundary = self.header("content-type")?.split("boundary=").nth(1)?;
+        let delimiter = format!("--{}", boundary.trim_matches('"'));
+        let disposition = format!("name=\"{}\"", name);
+        split(&self.body, delimiter.as_bytes()).into_iter()
+            .filter_map(|part| {
+                let part = part.strip_prefix(b"\r\n")?;
+                let end = find(part, b"\r\n\r\n")?;
+                let data = &part[end + 4..];
+                Some(FormPart {
+                    headers: String::from_utf8_lossy(&part[..end]).into_owned(),
+                    data: data.strip_suffix(b"\r\n").unwrap_or(data).to_vec(),
+                })
+            })
+            .find(|p| p.headers.contains(&disposition))
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Pieces of `data` between occurrences of `delimiter`
+fn split<'a>(mut data: &'a [u8], delimiter: &[u8]) -> Vec<&'a [u8]> {
+    let mut pieces = Vec::new();
+    while let Some(i) = find(data, delimiter) {
+        pieces.push(&data[..i]);
+        data = &data[i + delimiter.len()..];
+    }
+    pieces.push(data);
+    pieces
+}
+
+/// Builds a complete HTTP response with the given status line e.g.
+/// "200 OK" and body
+pub(crate) fn response(status: &str, body: &[u8]) -> Vec<u8> {
+    let mut response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n\r\n", status, body.len()).into_bytes();
+    response.extend_from_slice(body);
+    response
+}
+
+/// Response coveralls gives to a successful upload
+pub(crate) fn job_created() -> Vec<u8> {
+    response("200 OK", br#"{"message":"Job #1.1","url":"https://coveralls.io/jobs/1"}"#)
+}
+
+/// Server on a local port answering one connection per canned response
+pub(crate) struct MockServer {
+    port: u16,
+    handle: thread::JoinHandle<Vec<CapturedRequest>>,
+}
+
+impl MockServer {
+    pub fn start(responses: Vec<Vec<u8>>) -> MockServer {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = thread::spawn(move || responses.into_iter().map(|response| {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            let header_end = loop {
+                let n = stream.read(&mut buf).unwrap();
+                assert!(n > 0, "connection closed before the end of the headers");
+                request.extend_from_slice(&buf[..n]);
+                if let Some(i) = find(&request, b"\r\n\r\n") {
+                    break i;
+                }
+            };
+            let mut captured = CapturedRequest {
+                headers: String::from_utf8_lossy(&request[..header_end]).into_owned(),
+                body: Vec::new(),
+            };
+            // Saves curl waiting before it sends a large body
+            if captured.header("expect").is_some() {
+                stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").unwrap();
+            }
+            let length = captured.header("content-length").map_or(0, |v| v.parse::<usize>().unwrap());
+            while request.len() < header_end + 4 + length {
+                let n = stream.read(&mut buf).unwrap();
+                assert!(n > 0, "connection closed before the end of the body");
+                request.extend_from_slice(&buf[..n]);
+            }
+            captured.body = request[header_end + 4..].to_vec();
+            stream.write_all(&response).unwrap();
+            captured
+        }).collect());
+        MockServer { port, handle }
+    }
+
+    /// Jobs endpoint on this server, to pass to `send_to_endpoint`
+    pub fn url(&self) -> String {
+        format!("{}/api/v1/jobs", self.base_url())
+    }
+
+    /// Address of the server with no path
+    pub fn base_url(&self) -> String {
+        format!("http://127.0.0.1:{}", self.port)
+    }
+
+    /// Waits for every response to be sent and returns the requests received
+    pub fn requests(self) -> Vec<CapturedRequest> {
+        self.handle.join().unwrap()
+    }
+}
@@ -0,0 +1,81 @@
+//! Non-blocking uploads through reqwest, for callers running on an async
+//! runtime. Enabled by the `async` feature.
+//!
+//! The methods are named with an `_async` suffix rather than replacing the
+//! blocking `send_*` methods, so enabling the feature in one crate of a build
+//! can't change the signatures another crate in the same build relies on.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use futures_util::future::{self, Either, FutureExt};
+use reqwest::header::RETRY_AFTER;
+use reqwest::multipart::{Form, Part};
+use reqwest::Client;
+use {CoverallsError, HttpResponse};
+
+
+type Upload = Pin<Box<dyn Future<Output = Result<(), CoverallsError>> + Send>>;
+
+/// Timeouts and retries of the report being sent
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct Settings {
+    pub connect_timeout: Option<Duration>,
+    /// Longest wait for each read of the response
+    pub read_timeout: Option<Duration>,
+    pub retries: u32,
+}
+
+/// Posts the gzipped report as the `json_file` part of a multipart form, as
+/// `post_report` does for the blocking path. Rate limits and server errors
+/// are retried with the same delays. The client is built when first polled
+/// as its timers need the runtime.
+pub(crate) fn post_report(url: String, body: Vec<u8>, settings: Settings) -> Upload {
+    future::lazy(move |_| {
+        let mut builder = Client::builder();
+        if let Some(t) = settings.connect_timeout {
+            builder = builder.connect_timeout(t);
+        }
+        if let Some(t) = settings.read_timeout {
+            builder = builder.read_timeout(t);
+        }
+        builder.build()
+    }).then(move |client| match client {
+        Ok(client) => attempt(client, url, body, settings.retries),
+        Err(e) => future::ready(Err(CoverallsError::from(e))).boxed(),
+    }).boxed()
+}
+
+fn attempt(client: Client, url: String, body: Vec<u8>, retries: u32) -> Upload {
+    let part = match Part::bytes(body.clone()).file_name("report").mime_str("gzip/json") {
+        Ok(p) => p,
+        Err(e) => return future::ready(Err(CoverallsError::from(e))).boxed(),
+    };
+    let form = Form::new().part("json_file", part);
+    let request = client.post(&url).multipart(form).send();
+    request.then(move |response| {
+        let response = match response {
+            Ok(r) => r,
+            Err(e) => return Either::Left(future::ready(Err(CoverallsError::from(e)))),
+        };
+        let retry_after = response.headers()
+                                  .get(RETRY_AFTER)
+                                  .and_then(|v| v.to_str().ok())
+                                  .and_then(|v| v.trim().parse().ok())
+                                  .map(Duration::from_secs);
+        let status = HttpResponse {
+            code: u32::from(response.status().as_u16()),
+            retry_after,
+            body: Vec::new(),
+        };
+        if retries > 0 && status.retryable() {
+            let retry = tokio::time::sleep(status.retry_delay())
+                .then(move |_| attempt(client, url, body, retries - 1));
+            Either::Right(retry)
+        } else if (200..300).contains(&status.code) {
+            Either::Left(future::ready(Ok(())))
+        } else {
+            Either::Left(future::ready(Err(CoverallsError::Http(status.code))))
+        }
+    }).boxed()
+}
@@ -0,0 +1,211 @@
+//! Plain text coverage tables for printing in CI logs.
+
+use std::io::{self, IsTerminal, Write};
+use {CoverageStats, Source};
+
+
+/// Order of the rows in a summary table
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+pub enum SortOrder {
+    /// Lowest coverage first, files with no relevant lines last
+    #[default]
+    WorstFirst,
+    /// Alphabetically by file name
+    Name,
+    /// The order the sources were added to the report
+    Unsorted,
+}
+
+/// When to colour percentages with ANSI escape codes
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug, Default)]
+pub enum ColorMode {
+    /// Colour if stdout is a terminal, whatever the table is written to. Only
+    /// suited to writing to stdout, for other writers use `Never` or
+    /// `ColorMode::for_stream`.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    /// `Always` if `stream`, which the table will be written to, is a
+    /// terminal otherwise `Never`
+    pub fn for_stream<T: IsTerminal>(stream: &T) -> ColorMode {
+        if stream.is_terminal() {
+            ColorMode::Always
+        } else {
+            ColorMode::Never
+        }
+    }
+}
+
+/// Options for `CoverallsReport::print_summary`
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct SummaryOptions {
+    /// Row order
+    pub sort: SortOrder,
+    /// Maximum number of file rows to print, the total is always printed
+    pub max_rows: Option<usize>,
+    /// Colouring of percentages
+    pub color: ColorMode,
+    /// Width of the file name column, longer names are truncated in the middle
+    pub name_width: usize,
+}
+
+impl Default for SummaryOptions {
+    fn default() -> Self {
+        SummaryOptions {
+            sort: SortOrder::default(),
+            max_rows: None,
+            color: ColorMode::default(),
+            name_width: 50,
+        }
+    }
+}
+
+/// Shortens `name` to `width` characters by replacing the middle with "..."
+pub fn truncate_middle(name: &str, width: usize) -> String {
+    let chars = name.chars().collect::<Vec<_>>();
+    if chars.len() <= width {
+        return name.to_string();
+    }
+    if width <= 3 {
+        return chars[chars.len() - width..].iter().collect();
+    }
+    let keep = width - 3;
+    let front = keep / 2;
+    let back = keep - front;
+    let mut result = chars[..front].iter().collect::<String>();
+    result.push_str("...");
+    result.extend(chars[chars.len() - back..].iter());
+    result
+}
+
+fn format_percent(percent: Option<f64>, color: bool) -> String {
+    let text = match percent {
+        Some(p) => format!("{:>8.2}%", p),
+        None => format!("{:>9}", "-"),
+    };
+    match percent {
+        Some(p) if color => {
+            let code = if p >= 80.0 {
+                32
+            } else if p >= 50.0 {
+                33
+            } else {
+                31
+            };
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        },
+        _ => text,
+    }
+}
+
+/// Writes a table of the relevant and covered lines per source followed by a
+/// totals row.
+pub fn write<W: Write>(sources: &[Source], w: &mut W, opts: &SummaryOptions) -> io::Result<()> {
+    let color = match opts.color {
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+        ColorMode::Auto => io::stdout().is_terminal(),
+    };
+    let mut rows = sources.iter().map(|s| (s.name.as_str(), s.stats())).collect::<Vec<_>>();
+    match opts.sort {
+        SortOrder::WorstFirst => rows.sort_by(|a, b| {
+            let a_percent = a.1.line_percent().unwrap_or(f64::INFINITY);
+            let b_percent = b.1.line_percent().unwrap_or(f64::INFINITY);
+            a_percent.partial_cmp(&b_percent).unwrap().then(a.0.cmp(b.0))
+        }),
+        SortOrder::Name => rows.sort_by(|a, b| a.0.cmp(b.0)),
+        SortOrder::Unsorted => {},
+    }
+    let width = opts.name_width;
+    writeln!(w, "{:<width$} {:>9} {:>9} {:>9}", "File", "Relevant", "Covered", "Coverage", width = width)?;
+    let mut total = CoverageStats::default();
+    for stats in rows.iter().map(|r| &r.1) {
+        total.add(stats);
+    }
+    let shown = opts.max_rows.unwrap_or(rows.len()).min(rows.len());
+    for &(name, ref stats) in &rows[..shown] {
+        writeln!(w, "{:<width$} {:>9} {:>9} {}", truncate_middle(name, width), stats.relevant_lines,
+                 stats.covered_lines, format_percent(stats.line_percent(), color), width = width)?;
+    }
+    if shown < rows.len() {
+        writeln!(w, "... {} more files", rows.len() - shown)?;
+    }
+    writeln!(w, "{:<width$} {:>9} {:>9} {}", "Total", total.relevant_lines, total.covered_lines,
+             format_percent(total.line_percent(), color), width = width)?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use tempfile;
+    use summary::*;
+
+    fn fixture() -> Vec<Source> {
+        let source = |name: &str, coverage: Vec<Option<u64>>| Source {
+            name: name.to_string(),
+            coverage: coverage.into(),
+            ..Default::default()
+        };
+        vec![
+            source("src/lib.rs", vec![Some(1), Some(1), Some(0), None]),
+            source("src/deeply/nested/module/with/a/long/path/parser.rs", vec![Some(0), Some(0), Some(1)]),
+            source("src/empty.rs", vec![None]),
+            source("src/main.rs", vec![Some(3)]),
+        ]
+    }
+
+    #[test]
+    fn test_worst_first() {
+        let opts = SummaryOptions {
+            color: ColorMode::Never,
+            name_width: 30,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        write(&fixture(), &mut out, &opts).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\
+File                            Relevant   Covered  Coverage
+src/deeply/ne...path/parser.rs         3         1    33.33%
+src/lib.rs                             3         2    66.67%
+src/main.rs                            1         1   100.00%
+src/empty.rs                           0         0         -
+Total                                  7         4    57.14%
+");
+    }
+
+    #[test]
+    fn test_max_rows_and_color() {
+        let opts = SummaryOptions {
+            sort: SortOrder::Name,
+            max_rows: Some(1),
+            color: ColorMode::Always,
+            name_width: 12,
+        };
+        let mut out = Vec::new();
+        write(&fixture(), &mut out, &opts).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\
+File          Relevant   Covered  Coverage
+src/...er.rs         3         1 \x1b[31m   33.33%\x1b[0m
+... 3 more files
+Total                7         4 \x1b[33m   57.14%\x1b[0m
+");
+    }
+
+    #[test]
+    fn test_color_for_stream() {
+        let file = tempfile::tempfile().unwrap();
+        assert_eq!(ColorMode::for_stream(&file), ColorMode::Never);
+    }
+
+    #[test]
+    fn test_truncate_middle() {
+        assert_eq!(truncate_middle("short", 10), "short");
+        assert_eq!(truncate_middle("abcdefghij", 7), "ab...ij");
+        assert_eq!(truncate_middle("ünïcödé_fïlé", 8), "ün...ïlé");
+    }
+}
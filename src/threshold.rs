@@ -0,0 +1,164 @@
+//! Minimum coverage checks for failing CI builds.
+
+use std::error;
+use std::fmt;
+use glob::Pattern;
+use CoverallsReport;
+
+
+/// Minimum coverage percentages a report has to reach. Unset minimums aren't
+/// checked.
+#[derive(Clone, PartialEq, PartialOrd, Debug, Default)]
+pub struct ThresholdPolicy {
+    /// Minimum line coverage of the whole report
+    pub min_total: Option<f64>,
+    /// Minimum line coverage of each file
+    pub min_file: Option<f64>,
+    /// Files matching these aren't held to `min_file`
+    pub file_exemptions: Vec<Pattern>,
+    /// Minimum branch coverage of the whole report
+    pub min_branch: Option<f64>,
+}
+
+/// A single coverage minimum which wasn't reached
+#[derive(Clone, PartialEq, PartialOrd, Debug)]
+pub enum Failure {
+    Total { actual: f64, required: f64 },
+    File { name: String, actual: f64, required: f64 },
+    Branch { actual: f64, required: f64 },
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Failure::Total { actual, required } => {
+                write!(f, "total line coverage {:.2}% is below {:.2}%", actual, required)
+            },
+            Failure::File { ref name, actual, required } => {
+                write!(f, "{}: line coverage {:.2}% is below {:.2}%", name, actual, required)
+            },
+            Failure::Branch { actual, required } => {
+                write!(f, "total branch coverage {:.2}% is below {:.2}%", actual, required)
+            },
+        }
+    }
+}
+
+/// Every failure of a report against a `ThresholdPolicy`
+#[derive(Clone, PartialEq, PartialOrd, Debug)]
+pub struct ThresholdViolation {
+    pub failures: Vec<Failure>,
+}
+
+impl fmt::Display for ThresholdViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Coverage below the required minimum:")?;
+        for failure in &self.failures {
+            write!(f, "\n  {}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for ThresholdViolation {}
+
+/// Checks `report` against `policy`. Anything without relevant lines or
+/// branches has no percentage and so can't fail.
+pub fn enforce(report: &CoverallsReport, policy: &ThresholdPolicy) -> Result<(), ThresholdViolation> {
+    let mut failures = Vec::new();
+    let total = report.stats();
+    if let (Some(required), Some(actual)) = (policy.min_total, total.line_percent()) {
+        if actual < required {
+            failures.push(Failure::Total { actual, required });
+        }
+    }
+    if let Some(required) = policy.min_file {
+        for source in &report.source_files {
+            if policy.file_exemptions.iter().any(|p| p.matches(&source.name)) {
+                continue;
+            }
+            match source.stats().line_percent() {
+                Some(actual) if actual < required => failures.push(Failure::File {
+                    name: source.name.clone(),
+                    actual,
+                    required,
+                }),
+                _ => {},
+            }
+        }
+    }
+    if let (Some(required), Some(actual)) = (policy.min_branch, total.branch_percent()) {
+        if actual < required {
+            failures.push(Failure::Branch { actual, required });
+        }
+    }
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(ThresholdViolation { failures })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use threshold::*;
+    use {Identity, Source};
+
+    fn report() -> CoverallsReport {
+        let mut report = CoverallsReport::new(Identity::RepoToken(String::new().into()));
+        let sources = vec![
+            ("src/lib.rs", vec![Some(1), Some(1), Some(1), Some(0)], Some(vec![1, 0, 0, 1, 1, 0, 1, 0])),
+            ("src/generated.rs", vec![Some(0), Some(1)], None),
+            ("src/empty.rs", vec![None, None], None),
+        ];
+        for (name, coverage, branches) in sources {
+            report.add_source(Source {
+                name: name.to_string(),
+                coverage: coverage.into(),
+                branches,
+                ..Default::default()
+            });
+        }
+        report
+    }
+
+    #[test]
+    fn test_total_and_branch() {
+        let report = report();
+        let policy = ThresholdPolicy {
+            min_total: Some(66.0),
+            min_branch: Some(50.0),
+            ..Default::default()
+        };
+        assert_eq!(report.enforce(&policy), Ok(()));
+        let policy = ThresholdPolicy {
+            min_total: Some(70.0),
+            min_branch: Some(60.0),
+            ..Default::default()
+        };
+        let err = report.enforce(&policy).unwrap_err();
+        assert_eq!(err.failures.len(), 2);
+        assert_eq!(err.to_string(), "Coverage below the required minimum:
+  total line coverage 66.67% is below 70.00%
+  total branch coverage 50.00% is below 60.00%");
+    }
+
+    #[test]
+    fn test_per_file() {
+        let report = report();
+        let mut policy = ThresholdPolicy {
+            min_file: Some(60.0),
+            ..Default::default()
+        };
+        let err = report.enforce(&policy).unwrap_err();
+        assert_eq!(err.failures, vec![Failure::File {
+            name: "src/generated.rs".to_string(),
+            actual: 50.0,
+            required: 60.0,
+        }]);
+
+        policy.file_exemptions.push(Pattern::new("src/gen*").unwrap());
+        assert_eq!(report.enforce(&policy), Ok(()));
+    }
+}
@@ -0,0 +1,92 @@
+//! Helpers for naming sources in Cargo workspaces, where coverage tools give
+//! paths relative to the member crate rather than the repository.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde_json::{self, Value};
+use import::{lexical_normalize, normalize_name};
+use CoverallsError;
+
+
+fn is_workspace_manifest(manifest: &Path) -> bool {
+    fs::read_to_string(manifest)
+        .map(|m| m.lines().any(|l| l.trim_start().starts_with("[workspace")))
+        .unwrap_or(false)
+}
+
+/// Finds the workspace root for `start` as the outermost directory above it
+/// with a `Cargo.toml` containing a `[workspace]` section
+pub fn find_workspace_root(start: &Path) -> Option<PathBuf> {
+    let start = start.canonicalize().ok()?;
+    start.ancestors()
+        .filter(|d| is_workspace_manifest(&d.join("Cargo.toml")))
+        .last()
+        .map(|d| d.to_path_buf())
+}
+
+/// Reads the workspace root from the JSON printed by `cargo metadata`
+pub fn workspace_root_from_metadata(metadata: &str) -> Result<PathBuf, CoverallsError> {
+    let metadata: Value = serde_json::from_str(metadata)?;
+    match metadata["workspace_root"].as_str() {
+        Some(root) => Ok(PathBuf::from(root)),
+        None => Err(CoverallsError::Parse("cargo metadata has no workspace_root".to_string())),
+    }
+}
+
+/// Name for a source given relative to the member crate at `crate_dir`,
+/// relative to `repo_root` e.g. `crates/foo/src/lib.rs`. The repository root
+/// needn't be the workspace root.
+pub fn member_source_name(repo_root: &Path, crate_dir: &Path, path: &Path) -> Result<String, CoverallsError> {
+    let repo_root = lexical_normalize(repo_root);
+    let full = lexical_normalize(&crate_dir.join(path));
+    match full.strip_prefix(&repo_root) {
+        Ok(name) => Ok(normalize_name(&name.to_string_lossy())),
+        Err(_) => Err(CoverallsError::OutsideRoot(full)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tempfile::TempDir;
+    use workspace::*;
+
+    /// Repository with the workspace in a `rust` subdirectory and two members
+    fn workspace() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let ws = dir.path().join("rust");
+        fs::create_dir_all(dir.path().join(".git")).unwrap();
+        fs::create_dir_all(ws.join("crates/foo/src")).unwrap();
+        fs::create_dir_all(ws.join("crates/bar/src")).unwrap();
+        fs::write(ws.join("Cargo.toml"), "[workspace]\nmembers = [\"crates/*\"]\n").unwrap();
+        fs::write(ws.join("crates/foo/Cargo.toml"), "[package]\nname = \"foo\"\n").unwrap();
+        fs::write(ws.join("crates/bar/Cargo.toml"), "[package]\nname = \"bar\"\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_find_workspace_root() {
+        let dir = workspace();
+        let root = dir.path().canonicalize().unwrap();
+        assert_eq!(find_workspace_root(&root.join("rust/crates/foo/src")), Some(root.join("rust")));
+        assert_eq!(find_workspace_root(&root), None);
+
+        let metadata = format!(r#"{{"packages": [], "workspace_root": "{}"}}"#, root.join("rust").display());
+        assert_eq!(workspace_root_from_metadata(&metadata).unwrap(), root.join("rust"));
+        assert!(workspace_root_from_metadata("{}").is_err());
+    }
+
+    #[test]
+    fn test_member_source_name() {
+        let dir = workspace();
+        let root = dir.path().canonicalize().unwrap();
+        let ws = find_workspace_root(&root.join("rust/crates/bar")).unwrap();
+        let foo = member_source_name(&root, &ws.join("crates/foo"), "src/lib.rs".as_ref()).unwrap();
+        let bar = member_source_name(&root, &ws.join("crates/bar"), "src/lib.rs".as_ref()).unwrap();
+        assert_eq!(foo, "rust/crates/foo/src/lib.rs");
+        assert_eq!(bar, "rust/crates/bar/src/lib.rs");
+        let outside = member_source_name(&root.join("rust/crates/foo"), &ws.join("crates/bar"), "src/lib.rs".as_ref());
+        assert!(outside.is_err());
+    }
+}
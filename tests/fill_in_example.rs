@@ -11,10 +11,7 @@ use coveralls_api::*;
 #[test]
 fn test_submission() {
     let mut travis = true;
-    let mut secret_key = match std::env::var("TRAVIS_JOB_ID") {
-        Ok(key) => key,
-        Err(_) => String::new(),
-    };
+    let mut secret_key = std::env::var("TRAVIS_JOB_ID").unwrap_or_default();
     if secret_key.is_empty() {
         println!("Running using Coveralls key");
         travis = false;
@@ -30,14 +27,14 @@ fn test_submission() {
     abs_path.push(repo_path);
     assert!(abs_path.exists(), "Run the test from project root directory");
 
-    let mut lines: HashMap<usize, usize> = HashMap::new();
+    let mut lines: HashMap<usize, u64> = HashMap::new();
     lines.insert(4,0);
     lines.insert(5, 1);
     lines.insert(6, 2);
     lines.insert(7, 1);
 
-    let source = Source::new(&repo_path,
-                             &abs_path.as_path(),
+    let source = Source::new(repo_path,
+                             abs_path.as_path(),
                              &lines,
                              &None,
                              false).unwrap();
@@ -49,10 +46,11 @@ fn test_submission() {
             build_url: None,
             branch: None,
             pull_request: None,
+            job_number: None,
         };
-        Identity::ServiceToken(String::new(), serv)
+        Identity::ServiceToken(String::new().into(), serv)
     } else {
-        Identity::RepoToken(secret_key)
+        Identity::RepoToken(secret_key.into())
     };
     let mut report = CoverallsReport::new(id);
     report.add_source(source);